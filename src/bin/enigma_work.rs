@@ -0,0 +1,148 @@
+use std::{env, fs, path::Path, process, sync::atomic::AtomicBool};
+
+use enigma::*;
+
+// split a search into work unit files, run one unit, merge the results.
+//
+//     enigma_work split <cipher file> <rotor count> <units> <out dir> [scoring] [top n] [reflector]
+//     enigma_work process <unit file> <result file>
+//     enigma_work merge <top n> <result file>...
+//
+// split with 3 rotors use rotors I to V and reflector B or C.
+// with 4 it is the naval M4: three of rotors I to V, Beta or Gamma in
+// the slow end and the thin reflector B or C. other machines can be
+// split with `EnigmaWorkUnit::split` directly.
+
+const REFLECTOR_B: &str = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+const REFLECTOR_C: &str = "FVPJIAOYEDRZXWGCTKUQSBNMHL";
+const REFLECTOR_B_THIN: &str = "ENKQAUYWJICOPBLMDXZVFTHRGS";
+const REFLECTOR_C_THIN: &str = "RDOBJNTKVEHMLFCWZAXGYIPSUQ";
+
+const USAGE: &str = "usage:
+  enigma_work split <cipher file> <rotor count> <units> <out dir> [scoring] [top n] [reflector]
+  enigma_work process <unit file> <result file>
+  enigma_work merge <top n> <result file>...";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<&str>>();
+    let done = match args.as_slice() {
+        ["split", cipher, rotor_count, units, out, rest @ ..] => {
+            split(cipher, rotor_count, units, out, rest)
+        }
+        ["process", unit, result] => run(unit, result),
+        ["merge", top_n, results @ ..] if !results.is_empty() => merge(top_n, results),
+        _ => Err(USAGE.to_owned()),
+    };
+    if let Err(e) = done {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn split(
+    cipher: &str,
+    rotor_count: &str,
+    units: &str,
+    out: &str,
+    rest: &[&str],
+) -> Result<(), String> {
+    let cipher_text = fs::read_to_string(cipher)
+        .map_err(|e| format!("{}: {}", cipher, e))?
+        .to_uppercase()
+        .chars()
+        .filter(|c| c.is_ascii_uppercase())
+        .collect::<String>();
+    if cipher_text.is_empty() {
+        return Err(format!("{}: no letters to search", cipher));
+    }
+    let rotor_count = number(rotor_count)?;
+    let units = number(units)?;
+    let scoring = match rest.first() {
+        Some(s) => EnigmaScoring::parse(s).ok_or(format!("unknown scoring {}", s))?,
+        None => EnigmaScoring::Bigram(EnigmaLanguage::German),
+    };
+    let top_n = rest.get(1).map_or(Ok(10), |n| number(n))?;
+    let reflector = match (rotor_count, rest.get(2).copied().unwrap_or("B")) {
+        (3, "B") => REFLECTOR_B,
+        (3, "C") => REFLECTOR_C,
+        (4, "B") => REFLECTOR_B_THIN,
+        (4, "C") => REFLECTOR_C_THIN,
+        (3 | 4, r) => return Err(format!("unknown reflector {}, should be B or C", r)),
+        _ => return Err("rotor count should be 3, or 4 for the M4".to_owned()),
+    };
+
+    // index 5 and 6 are the greek wheels, only used by the M4
+    let rotors = [
+        EnigmaRotorSetting::I,
+        EnigmaRotorSetting::II,
+        EnigmaRotorSetting::III,
+        EnigmaRotorSetting::IV,
+        EnigmaRotorSetting::V,
+        EnigmaRotorSetting::BETA,
+        EnigmaRotorSetting::GAMMA,
+    ];
+    let mut orders = EnigmaRotorSetting::wheel_orders(5, 3);
+    if rotor_count == 4 {
+        orders = orders
+            .into_iter()
+            .flat_map(|order| [5, 6].map(|greek| [order.clone(), vec![greek]].concat()))
+            .collect();
+    }
+    let search = EnigmaParallelSearch::new(&rotors, reflector, top_n);
+    let items = search.work_items_for(&orders);
+    let units = EnigmaWorkUnit::split(
+        &rotors,
+        reflector,
+        &cipher_text,
+        scoring,
+        top_n,
+        &items,
+        units,
+    );
+
+    fs::create_dir_all(out).map_err(|e| format!("{}: {}", out, e))?;
+    for unit in units.iter() {
+        let path = Path::new(out).join(format!("unit_{}.txt", unit.id));
+        fs::write(&path, unit.save()).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    println!("{} units written to {}", units.len(), out);
+    Ok(())
+}
+
+fn run(unit: &str, result: &str) -> Result<(), String> {
+    let s = fs::read_to_string(unit).map_err(|e| format!("{}: {}", unit, e))?;
+    let unit = EnigmaWorkUnit::load(&s).map_err(|e| format!("{}: {:?}", unit, e))?;
+    let cancel = AtomicBool::new(false);
+    let found = unit.process(
+        &mut |progress| eprint!("\r{}/{}", progress.done, progress.total),
+        &cancel,
+    );
+    eprintln!();
+    fs::write(result, found.save()).map_err(|e| format!("{}: {}", result, e))
+}
+
+fn merge(top_n: &str, results: &[&str]) -> Result<(), String> {
+    let top_n = number(top_n)?;
+    let results = results
+        .iter()
+        .map(|path| {
+            let s = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            EnigmaWorkResult::load(&s).map_err(|e| format!("{}: {:?}", path, e))
+        })
+        .collect::<Result<Vec<EnigmaWorkResult>, String>>()?;
+
+    let missing = EnigmaWorkResult::missing(&results);
+    if !missing.is_empty() {
+        eprintln!("units not done yet: {:?}", missing);
+    }
+    for candidate in EnigmaWorkResult::merge(&results, top_n) {
+        println!("{:.4} {}", candidate.score, candidate.key);
+    }
+    Ok(())
+}
+
+fn number(s: &str) -> Result<usize, String> {
+    s.parse::<usize>()
+        .map_err(|_| format!("not a number: {}", s))
+}
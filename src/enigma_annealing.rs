@@ -0,0 +1,339 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::{
+    enigma_machine::*, enigma_procedure::*, enigma_random::*, enigma_stats::*, enigma_types::*,
+};
+
+// ciphertext-only search over the whole key by simulated annealing.
+// from a random key, keep changing a small part of it (a wire, a window,
+// a ring, two rotors) and keep the change when the decrypt score better.
+// a worse key is still taken now and then, more often while the
+// temperature is high, so the search can climb out of a local best.
+// the temperature go down round by round, then the search start again
+// from a new random key.
+// everything needed to carry on, random state included, is saved to a
+// file every few rounds, so a run that is stopped can pick up from there.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaAnnealingState {
+    pub restart: usize,
+    // rounds done in this restart
+    pub round: usize,
+    pub temperature: f64,
+    pub random_state: u64,
+    pub current: EnigmaKey,
+    pub current_score: f64,
+    pub best: EnigmaKey,
+    pub best_score: f64,
+}
+
+pub struct EnigmaAnnealing<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    rotor_count: usize,
+    // temperature at the start of every restart, in units of the fitness
+    pub start_temperature: f64,
+    // temperature is multiplied by this after every round
+    pub cooling: f64,
+    pub rounds: usize,
+    pub steps_per_round: usize,
+    pub restarts: usize,
+    pub max_wires: usize,
+    // where to save the state, and after how many rounds
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_every: usize,
+}
+
+impl EnigmaAnnealingState {
+    // one `name value` line for each field
+    pub fn save(&self) -> String {
+        format!(
+            "restart {}\nround {}\ntemperature {}\nrandom {}\ncurrent {}\ncurrent_score {}\nbest {}\nbest_score {}\n",
+            self.restart,
+            self.round,
+            self.temperature,
+            self.random_state,
+            self.current,
+            self.current_score,
+            self.best,
+            self.best_score
+        )
+    }
+
+    pub fn load(s: &str) -> Result<Self, EnigmaCheckpointError> {
+        let mut lines = s.lines().map(|line| line.split_once(' '));
+        let mut field = |name: &'static str| match lines.next() {
+            Some(Some((n, value))) if n == name => Ok((name, value.to_owned())),
+            _ => Err(EnigmaCheckpointError::MissingField(name.to_owned())),
+        };
+        let number = |(name, value): (&str, String)| {
+            value
+                .parse::<usize>()
+                .map_err(|_| EnigmaCheckpointError::InvalidValue(name.to_owned()))
+        };
+        let real = |(name, value): (&str, String)| {
+            value
+                .parse::<f64>()
+                .map_err(|_| EnigmaCheckpointError::InvalidValue(name.to_owned()))
+        };
+        let key = |(name, value): (&str, String)| {
+            EnigmaKey::parse(&value)
+                .map_err(|e| EnigmaCheckpointError::InvalidKey(name.to_owned(), e))
+        };
+
+        Ok(Self {
+            restart: number(field("restart")?)?,
+            round: number(field("round")?)?,
+            temperature: real(field("temperature")?)?,
+            random_state: field("random")?
+                .1
+                .parse::<u64>()
+                .map_err(|_| EnigmaCheckpointError::InvalidValue("random".to_owned()))?,
+            current: key(field("current")?)?,
+            current_score: real(field("current_score")?)?,
+            best: key(field("best")?)?,
+            best_score: real(field("best_score")?)?,
+        })
+    }
+}
+
+impl<'a> EnigmaAnnealing<'a> {
+    // rotors to pick from, and how many go in the machine
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>], reflector: &'a str, rotor_count: usize) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            rotor_count,
+            start_temperature: 0.02,
+            cooling: 0.9,
+            rounds: 40,
+            steps_per_round: 200,
+            restarts: 3,
+            max_wires: 10,
+            checkpoint: None,
+            checkpoint_every: 5,
+        }
+    }
+
+    // fresh search from a random key
+    pub fn start(
+        &self,
+        cipher_text: &str,
+        fitness: &dyn EnigmaFitness,
+        seed: u64,
+    ) -> EnigmaAnnealingState {
+        let mut random = EnigmaRandom::new(seed);
+        let key = self.random_key(&mut random);
+        self.start_with_key(cipher_text, fitness, &key, random.state())
+    }
+
+    // fresh search from a key found some other way,
+    // e.g. wheel order and start position from the index of coincidence search
+    pub fn start_with_key(
+        &self,
+        cipher_text: &str,
+        fitness: &dyn EnigmaFitness,
+        key: &EnigmaKey,
+        random_state: u64,
+    ) -> EnigmaAnnealingState {
+        let score = self.score(cipher_text, fitness, key);
+        EnigmaAnnealingState {
+            restart: 0,
+            round: 0,
+            temperature: self.start_temperature,
+            random_state,
+            current: key.clone(),
+            current_score: score,
+            best: key.clone(),
+            best_score: score,
+        }
+    }
+
+    // carry on from the checkpoint file,
+    // the keys in it must fit the rotors of this search
+    pub fn resume(&self) -> Result<EnigmaAnnealingState, EnigmaCheckpointError> {
+        let path = self
+            .checkpoint
+            .as_ref()
+            .ok_or(EnigmaCheckpointError::Io(io::ErrorKind::NotFound))?;
+        let saved = fs::read_to_string(path).map_err(|e| EnigmaCheckpointError::Io(e.kind()))?;
+        let state = EnigmaAnnealingState::load(&saved)?;
+        for (name, key) in [("current", &state.current), ("best", &state.best)] {
+            EnigmaMachine::new_with_key(&self.rotors, self.reflector, key)
+                .map_err(|e| EnigmaCheckpointError::InvalidKey(name.to_owned(), e))?;
+        }
+        Ok(state)
+    }
+
+    // run until every restart is done, or until `max_rounds` more rounds
+    // are done if given. return true when the search is finished.
+    pub fn run(
+        &self,
+        cipher_text: &str,
+        fitness: &dyn EnigmaFitness,
+        state: &mut EnigmaAnnealingState,
+        max_rounds: Option<usize>,
+    ) -> io::Result<bool> {
+        if !is_enigma_text(cipher_text) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not enigma text",
+            ));
+        }
+
+        let mut random = EnigmaRandom::new_with_state(state.random_state);
+        let mut done = 0;
+        loop {
+            if state.round >= self.rounds {
+                if state.restart >= self.restarts {
+                    break;
+                }
+                // next restart, from a random key
+                state.restart += 1;
+                state.round = 0;
+                state.temperature = self.start_temperature;
+                state.current = self.random_key(&mut random);
+                state.current_score = self.score(cipher_text, fitness, &state.current);
+                state.random_state = random.state();
+                continue;
+            }
+            if max_rounds.is_some_and(|max| done >= max) {
+                self.save(state)?;
+                return Ok(false);
+            }
+
+            for _ in 0..self.steps_per_round {
+                let key = self.neighbour(&state.current, &mut random);
+                let score = self.score(cipher_text, fitness, &key);
+                let delta = score - state.current_score;
+                if delta >= 0.0 || random.next_f64() < (delta / state.temperature).exp() {
+                    state.current = key;
+                    state.current_score = score;
+                    if score > state.best_score {
+                        state.best = state.current.clone();
+                        state.best_score = score;
+                    }
+                }
+            }
+            state.round += 1;
+            state.temperature *= self.cooling;
+            state.random_state = random.state();
+            done += 1;
+
+            if self.checkpoint_every > 0 && state.round.is_multiple_of(self.checkpoint_every) {
+                self.save(state)?;
+            }
+        }
+
+        self.save(state)?;
+        Ok(true)
+    }
+
+    fn save(&self, state: &EnigmaAnnealingState) -> io::Result<()> {
+        match self.checkpoint.as_ref() {
+            // write aside first so a stop half way leave the old file whole
+            Some(path) => {
+                let temp = path.with_extension("tmp");
+                fs::write(&temp, state.save())?;
+                fs::rename(&temp, path)
+            }
+            None => Ok(()),
+        }
+    }
+
+    // a key that can't be built score lowest, so it is never kept
+    fn score(&self, cipher_text: &str, fitness: &dyn EnigmaFitness, key: &EnigmaKey) -> f64 {
+        match EnigmaMachine::new_with_key(&self.rotors, self.reflector, key) {
+            Ok(mut machine) => fitness.score(&machine.encode_str(cipher_text)),
+            Err(_) => f64::NEG_INFINITY,
+        }
+    }
+
+    fn random_key(&self, random: &mut EnigmaRandom) -> EnigmaKey {
+        let mut available = (0..self.rotors.len()).collect::<Vec<usize>>();
+        let rotor_order = (0..self.rotor_count.min(self.rotors.len()))
+            .map(|_| available.remove(random.next_index(available.len())))
+            .collect::<Vec<usize>>();
+        let count = rotor_order.len();
+
+        // ring of the slow rotors can be left, see `EnigmaRingSearch`
+        let ring = (0..count)
+            .map(|idx| {
+                if idx < 2 {
+                    random.next_letters(1)
+                } else {
+                    "A".to_owned()
+                }
+            })
+            .collect::<String>();
+        EnigmaKey {
+            rotor_order,
+            ring,
+            window: random.next_letters(count),
+            wires: vec![],
+        }
+    }
+
+    // key next to this one, most of the time a change of the plugboard
+    fn neighbour(&self, key: &EnigmaKey, random: &mut EnigmaRandom) -> EnigmaKey {
+        let mut ret = key.clone();
+        let count = ret.rotor_order.len();
+        let roll = random.next_index(100);
+
+        if roll < 70 || count == 0 {
+            self.change_wires(&mut ret.wires, random);
+        } else if roll < 85 {
+            // move one window
+            let idx = random.next_index(count);
+            Self::shift(&mut ret.window, idx, 1 + random.next_index(25));
+        } else if roll < 95 {
+            // turn a ring and the window with it, only the turnover move
+            let idx = random.next_index(count.min(2));
+            let by = 1 + random.next_index(25);
+            Self::shift(&mut ret.ring, idx, by);
+            Self::shift(&mut ret.window, idx, by);
+        } else {
+            // swap two rotors, or put in one that is not used
+            let idx = random.next_index(count);
+            let other = random.next_index(self.rotors.len());
+            match ret.rotor_order.iter().position(|&r| r == other) {
+                Some(at) => ret.rotor_order.swap(idx, at),
+                None => ret.rotor_order[idx] = other,
+            }
+        }
+        ret
+    }
+
+    fn change_wires(&self, wires: &mut Vec<EnigmaPlugBoardWire>, random: &mut EnigmaRandom) {
+        let a = (random.next_index(26) as u8 + b'A') as char;
+        let b = (random.next_index(26) as u8 + b'A') as char;
+        let plugged = |c: char, wires: &[EnigmaPlugBoardWire]| {
+            wires.iter().position(|w| w.0 == c || w.1 == c)
+        };
+
+        match (plugged(a, wires), plugged(b, wires)) {
+            // already wired together, pull it out
+            (Some(x), Some(y)) if x == y => {
+                wires.remove(x);
+            }
+            _ if a == b => {}
+            (x, y) => {
+                // pull out whatever a and b are plugged to, then wire them
+                let mut taken = [x, y].into_iter().flatten().collect::<Vec<usize>>();
+                taken.sort();
+                taken.into_iter().rev().for_each(|idx| {
+                    wires.remove(idx);
+                });
+                if wires.len() < self.max_wires {
+                    wires.push(EnigmaPlugBoardWire(a.min(b), a.max(b)));
+                }
+            }
+        }
+    }
+
+    fn shift(letters: &mut String, idx: usize, by: usize) {
+        let mut bytes = letters.clone().into_bytes();
+        bytes[idx] = (bytes[idx] - b'A' + by as u8) % 26 + b'A';
+        *letters = String::from_utf8(bytes).unwrap();
+    }
+}
@@ -0,0 +1,415 @@
+use crate::{enigma_procedure::*, enigma_types::*};
+
+// Banburismus.
+// two messages whose start positions are close are enciphered with the
+// same key for a stretch, one some letters after the other. set them in
+// depth at that distance and letters repeat as often as in plain text
+// (about 1 in 13 for german), not 1 in 26 like random letters.
+// each repeat and each miss is weighed in decibans, tenths of the log of
+// how much more likely the alignment is real than random.
+//
+// the start positions are not known, only the indicators: the message
+// keys enciphered at the grundstellung of the day. every message of the
+// day is enciphered at the same grundstellung, so the same letter at the
+// same place of two indicators is the same window letter.
+// two indicators that differ only in the last letter start with the same
+// middle and left rotor, some fast rotor steps apart, and the best depth
+// tell how many. the distances chain indicator letters together. an
+// indicator letter and its window letter are swapped by the machine, which
+// never send a letter to itself, so only a few ways to lay a chain on the
+// alphabet are left (scritchmus). a depth is only there when the fast rotor
+// didn't turn the middle one over on the way, which rule out rotors with
+// the notch in between. the middle rotor is done the same way, with
+// indicators that differ in the middle letter and the turnovers of the fast
+// rotor counted. only the longest chain of each rotor is used.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaBanburismusMessage {
+    // message key enciphered at the grundstellung in the order it was typed,
+    // left to right, as it come out of the indicator once the bigram table
+    // is undone. so the fast rotor letter is the last one
+    pub indicator: String,
+    pub cipher_text: String,
+}
+
+// `first` sit `offset` letters ahead of `second`,
+// the letter at `offset + i` of the first is over the letter at `i` of the second
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaDepthScore {
+    pub first: usize,
+    pub second: usize,
+    pub offset: usize,
+    pub overlap: usize,
+    pub repeats: usize,
+    pub decibans: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaBanburismusHypothesis {
+    // index into the rotors given, fast rotor first then the middle one
+    pub rotor_order: Vec<usize>,
+    // (indicator letter, window letter) of every letter the chains reach,
+    // empty for the middle rotor when no depth tie its letters
+    pub fast_key: Vec<(char, char)>,
+    pub middle_key: Vec<(char, char)>,
+    // every depth the hypothesis rest on
+    pub depths: Vec<EnigmaDepthScore>,
+    pub decibans: f64,
+}
+
+pub struct EnigmaBanburismus<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    // how often letters repeat between two plain texts in depth,
+    // the index of coincidence of the language
+    pub repeat_rate: f64,
+    // depths shorter than this are not counted
+    pub min_overlap: usize,
+    // best alignment of two messages below this is taken as chance
+    pub min_decibans: f64,
+}
+
+// depth between two indicator letters, the window of `to` is
+// `steps` clicks of the rotor after the window of `from`
+#[derive(Clone, Debug)]
+struct EnigmaBanburismusLink {
+    depth: EnigmaDepthScore,
+    from: usize,
+    to: usize,
+    steps: usize,
+}
+
+// indicator letters tied together by depths
+#[derive(Clone, Debug)]
+struct EnigmaBanburismusChain {
+    // clicks from the first letter of the chain, None when not in it
+    clicks: [Option<usize>; 26],
+    links: Vec<EnigmaBanburismusLink>,
+}
+
+impl<'a> EnigmaBanburismus<'a> {
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>]) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            repeat_rate: 0.076,
+            min_overlap: 20,
+            min_decibans: 20.0,
+        }
+    }
+
+    // score two messages set in depth at `offset`
+    pub fn score(
+        &self,
+        messages: &[EnigmaBanburismusMessage],
+        first: usize,
+        second: usize,
+        offset: usize,
+    ) -> EnigmaDepthScore {
+        let a = messages[first].cipher_text.as_bytes();
+        let b = messages[second].cipher_text.as_bytes();
+        let overlap = a.len().saturating_sub(offset).min(b.len());
+        let repeats = a
+            .iter()
+            .skip(offset)
+            .zip(b.iter())
+            .filter(|(x, y)| x == y)
+            .count();
+
+        let random = 1.0 / 26.0;
+        let hit = 10.0 * (self.repeat_rate / random).log10();
+        let miss = 10.0 * ((1.0 - self.repeat_rate) / (1.0 - random)).log10();
+        EnigmaDepthScore {
+            first,
+            second,
+            offset,
+            overlap,
+            repeats,
+            decibans: repeats as f64 * hit + (overlap - repeats) as f64 * miss,
+        }
+    }
+
+    // slide the second message along the first, best alignment first
+    pub fn slide(
+        &self,
+        messages: &[EnigmaBanburismusMessage],
+        first: usize,
+        second: usize,
+    ) -> Vec<EnigmaDepthScore> {
+        let len = messages[first].cipher_text.len();
+        let mut ret = (0..len.saturating_sub(self.min_overlap) + 1)
+            .map(|offset| self.score(messages, first, second, offset))
+            .filter(|score| score.overlap >= self.min_overlap)
+            .collect::<Vec<EnigmaDepthScore>>();
+        ret.sort_by(|a, b| b.decibans.total_cmp(&a.decibans));
+        ret
+    }
+
+    // every ordered pair of different rotors for the fast and middle place
+    pub fn run(
+        &self,
+        messages: &[EnigmaBanburismusMessage],
+    ) -> Result<Vec<EnigmaBanburismusHypothesis>, EnigmaMessageError> {
+        let orders = EnigmaRotorSetting::wheel_orders(self.rotors.len(), 2);
+        self.run_wheel_orders(messages, &orders)
+    }
+
+    // best hypothesis first, for every way the chains fit the alphabet
+    pub fn run_wheel_orders(
+        &self,
+        messages: &[EnigmaBanburismusMessage],
+        orders: &[Vec<usize>],
+    ) -> Result<Vec<EnigmaBanburismusHypothesis>, EnigmaMessageError> {
+        let len = messages.first().map_or(0, |m| m.indicator.len());
+        for message in messages {
+            if len < 2 || message.indicator.len() != len || !is_enigma_text(&message.indicator) {
+                return Err(EnigmaMessageError::InvalidKey);
+            }
+            if !is_enigma_text(&message.cipher_text) {
+                return Err(EnigmaMessageError::InvalidText);
+            }
+        }
+
+        let fast_chain = match Self::chain(self.fast_links(messages)) {
+            Some(chain) => chain,
+            None => return Ok(vec![]),
+        };
+        let mut ret = vec![];
+        for order in orders {
+            let (fast, middle) = (self.rotors[order[0]], self.rotors[order[1]]);
+            let fast_notch = (fast.notch as u8 - b'A') as usize;
+            for fast_key in Self::keys(&fast_chain, fast_notch) {
+                let middle_links = self.middle_links(messages, &fast_key, fast_notch);
+                let mut depths = fast_chain.depths();
+                let fast_key_letters = Self::key_letters(&fast_key);
+
+                let middle_chain = match Self::chain(middle_links) {
+                    Some(chain) => chain,
+                    None => {
+                        ret.push(EnigmaBanburismusHypothesis {
+                            rotor_order: order.clone(),
+                            fast_key: fast_key_letters,
+                            middle_key: vec![],
+                            decibans: depths.iter().map(|d| d.decibans).sum(),
+                            depths,
+                        });
+                        continue;
+                    }
+                };
+                depths.extend(middle_chain.depths());
+                let middle_notch = (middle.notch as u8 - b'A') as usize;
+                for middle_key in Self::keys(&middle_chain, middle_notch) {
+                    ret.push(EnigmaBanburismusHypothesis {
+                        rotor_order: order.clone(),
+                        fast_key: fast_key_letters.clone(),
+                        middle_key: Self::key_letters(&middle_key),
+                        decibans: depths.iter().map(|d| d.decibans).sum(),
+                        depths: depths.clone(),
+                    });
+                }
+            }
+        }
+        ret.sort_by(|a, b| b.decibans.total_cmp(&a.decibans));
+        Ok(ret)
+    }
+
+    // messages whose indicators differ only in the last letter,
+    // less than a turn of the fast rotor apart
+    fn fast_links(&self, messages: &[EnigmaBanburismusMessage]) -> Vec<EnigmaBanburismusLink> {
+        let mut ret = vec![];
+        for i in 0..messages.len() {
+            for j in i + 1..messages.len() {
+                let (a, b) = (
+                    messages[i].indicator.as_bytes(),
+                    messages[j].indicator.as_bytes(),
+                );
+                let n = a.len() - 1;
+                if a[..n] != b[..n] || a[n] == b[n] {
+                    continue;
+                }
+                let best = [(i, j), (j, i)]
+                    .iter()
+                    .flat_map(|&(first, second)| {
+                        (1..26).map(move |offset| self.score(messages, first, second, offset))
+                    })
+                    .filter(|score| score.overlap >= self.min_overlap)
+                    .max_by(|x, y| x.decibans.total_cmp(&y.decibans));
+                if let Some(depth) = best.filter(|d| d.decibans >= self.min_decibans) {
+                    ret.push(EnigmaBanburismusLink {
+                        from: Self::letter(&messages[depth.first], n),
+                        to: Self::letter(&messages[depth.second], n),
+                        steps: depth.offset,
+                        depth,
+                    });
+                }
+            }
+        }
+        ret
+    }
+
+    // messages whose indicators differ in the middle letter and maybe the
+    // last one, with the window of the fast rotor known for both
+    fn middle_links(
+        &self,
+        messages: &[EnigmaBanburismusMessage],
+        fast_key: &[Option<usize>; 26],
+        fast_notch: usize,
+    ) -> Vec<EnigmaBanburismusLink> {
+        let mut ret = vec![];
+        for i in 0..messages.len() {
+            for j in i + 1..messages.len() {
+                let (a, b) = (
+                    messages[i].indicator.as_bytes(),
+                    messages[j].indicator.as_bytes(),
+                );
+                let n = a.len() - 2;
+                if a[..n] != b[..n] || a[n] == b[n] {
+                    continue;
+                }
+                let mut best: Option<(EnigmaDepthScore, usize)> = None;
+                for (first, second) in [(i, j), (j, i)] {
+                    let fast = |m: usize| fast_key[Self::letter(&messages[m], n + 1)];
+                    let (from, to) = match (fast(first), fast(second)) {
+                        (Some(from), Some(to)) => (from, to),
+                        _ => continue,
+                    };
+                    let max = messages[first]
+                        .cipher_text
+                        .len()
+                        .saturating_sub(self.min_overlap);
+                    for offset in ((to + 26 - from) % 26..=max).step_by(26) {
+                        // the middle rotor move each time the fast one leave its notch
+                        let turnovers = (0..offset)
+                            .filter(|k| (from + k) % 26 == fast_notch)
+                            .count();
+                        if turnovers == 0 {
+                            continue;
+                        }
+                        let score = self.score(messages, first, second, offset);
+                        if best
+                            .as_ref()
+                            .is_none_or(|(b, _)| b.decibans < score.decibans)
+                        {
+                            best = Some((score, turnovers));
+                        }
+                    }
+                }
+                if let Some((depth, steps)) = best.filter(|(d, _)| d.decibans >= self.min_decibans)
+                {
+                    ret.push(EnigmaBanburismusLink {
+                        from: Self::letter(&messages[depth.first], n),
+                        to: Self::letter(&messages[depth.second], n),
+                        steps,
+                        depth,
+                    });
+                }
+            }
+        }
+        ret
+    }
+
+    // tie letters together, best depth first, and leave out depths that
+    // disagree with the ones already in. the longest chain is kept.
+    fn chain(mut links: Vec<EnigmaBanburismusLink>) -> Option<EnigmaBanburismusChain> {
+        links.sort_by(|a, b| b.depth.decibans.total_cmp(&a.depth.decibans));
+        let mut chains: Vec<EnigmaBanburismusChain> = vec![];
+        for link in links {
+            let mut from = EnigmaBanburismusChain::take(&mut chains, link.from);
+            if let Some(to) = from.clicks[link.to] {
+                if (from.clicks[link.from].unwrap() + link.steps) % 26 == to {
+                    from.links.push(link);
+                }
+                chains.push(from);
+                continue;
+            }
+            let to = EnigmaBanburismusChain::take(&mut chains, link.to);
+            match from.join(&to, link) {
+                Some(joined) => chains.push(joined),
+                None => chains.extend([from, to]),
+            }
+        }
+        chains
+            .into_iter()
+            .filter(|chain| !chain.links.is_empty())
+            .max_by_key(|chain| chain.clicks.iter().flatten().count())
+    }
+
+    // window of every letter in the chain, for each way the chain can be
+    // laid on the alphabet with the rotor notch clear of every depth
+    fn keys(chain: &EnigmaBanburismusChain, notch: usize) -> Vec<[Option<usize>; 26]> {
+        (0..26)
+            .filter_map(|shift| {
+                let mut key = [None; 26];
+                chain
+                    .clicks
+                    .iter()
+                    .enumerate()
+                    .for_each(|(letter, c)| key[letter] = c.map(|c| (c + shift) % 26));
+                // swapped by the machine, and never the letter itself
+                let swapped = key
+                    .iter()
+                    .enumerate()
+                    .all(|(letter, &window)| match window {
+                        Some(window) => window != letter && key[window].is_none_or(|w| w == letter),
+                        None => true,
+                    });
+                let clear = chain.links.iter().all(|link| {
+                    let start = key[link.from].unwrap();
+                    (0..link.steps).all(|k| (start + k) % 26 != notch)
+                });
+                (swapped && clear).then_some(key)
+            })
+            .collect()
+    }
+
+    fn key_letters(key: &[Option<usize>; 26]) -> Vec<(char, char)> {
+        key.iter()
+            .enumerate()
+            .filter_map(|(letter, window)| {
+                window.map(|w| ((letter as u8 + b'A') as char, (w as u8 + b'A') as char))
+            })
+            .collect()
+    }
+
+    fn letter(message: &EnigmaBanburismusMessage, idx: usize) -> usize {
+        (message.indicator.as_bytes()[idx] - b'A') as usize
+    }
+}
+
+impl EnigmaBanburismusChain {
+    // the chain `letter` is in, or a chain of just that letter
+    fn take(chains: &mut Vec<Self>, letter: usize) -> Self {
+        match chains.iter().position(|c| c.clicks[letter].is_some()) {
+            Some(idx) => chains.remove(idx),
+            None => {
+                let mut clicks = [None; 26];
+                clicks[letter] = Some(0);
+                Self {
+                    clicks,
+                    links: vec![],
+                }
+            }
+        }
+    }
+
+    // turn `other` so the link fit, None if two letters end up at one place
+    fn join(&self, other: &Self, link: EnigmaBanburismusLink) -> Option<Self> {
+        let at = (self.clicks[link.from]? + link.steps) % 26;
+        let shift = (at + 26 - other.clicks[link.to]?) % 26;
+        let mut ret = self.clone();
+        for (letter, c) in other.clicks.iter().enumerate() {
+            if let Some(c) = c {
+                let c = (c + shift) % 26;
+                if ret.clicks.contains(&Some(c)) {
+                    return None;
+                }
+                ret.clicks[letter] = Some(c);
+            }
+        }
+        ret.links.extend(other.links.iter().cloned());
+        ret.links.push(link);
+        Some(ret)
+    }
+
+    fn depths(&self) -> Vec<EnigmaDepthScore> {
+        self.links.iter().map(|link| link.depth.clone()).collect()
+    }
+}
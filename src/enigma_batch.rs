@@ -0,0 +1,226 @@
+use crate::{
+    enigma_component::*, enigma_machine::*, enigma_procedure::*, enigma_stats::*, enigma_types::*,
+};
+
+// decrypt one cipher text under many keys at once.
+// a search try thousands of keys on the same text, and building an
+// `EnigmaMachine` for each (rotors behind `Rc`, wired one by one) cost
+// more than the decrypt itself. here every key is a column: rotor
+// positions, rings, notches, wirings and plugboard of all keys are kept in
+// flat arrays, one slot after the other, and each letter of the text is
+// run through all keys in simple loops over those arrays, which the
+// compiler can turn into vector code.
+// the result is the same as `EnigmaMachine::new_with_key` and `encode_str`,
+// stepping included.
+
+pub struct EnigmaBatch {
+    keys: usize,
+    rotor_count: usize,
+    // [slot * keys + key]
+    offsets: Vec<u8>,
+    rings: Vec<u8>,
+    notches: Vec<u8>,
+    // [(slot * keys + key) * 26 + letter]
+    forward: Vec<u8>,
+    backward: Vec<u8>,
+    // [key * 26 + letter]
+    plug_board: Vec<u8>,
+    reflector: [u8; 26],
+    entry_forward: [u8; 26],
+    entry_backward: [u8; 26],
+}
+
+impl EnigmaBatch {
+    // `rotors` is the list the keys' rotor order index into.
+    // every key must use the same number of rotors.
+    pub fn new(
+        rotors: &[EnigmaRotorSetting],
+        reflector: &str,
+        keys: &[EnigmaKey],
+    ) -> Result<Self, EnigmaKeyError> {
+        let rotor_count = keys.first().map_or(0, |key| key.rotor_order.len());
+        for key in keys.iter() {
+            if key.rotor_order.len() != rotor_count {
+                return Err(EnigmaKeyError::RotorCountMismatch);
+            }
+            if let Some(&idx) = key.rotor_order.iter().find(|&&idx| idx >= rotors.len()) {
+                return Err(EnigmaKeyError::InvalidRotor(idx));
+            }
+            let letters = |s: &str| s.bytes().all(|b| b.is_ascii_uppercase());
+            if !letters(&key.ring) || !letters(&key.window) {
+                return Err(EnigmaKeyError::InvalidLetters);
+            }
+        }
+
+        // read the tables back from the parts the machine is made of,
+        // so they can't be wired any different
+        let mut tables = vec![];
+        for &setting in rotors.iter() {
+            let mut rotor = EnigmaRotor::new();
+            if rotor
+                .set_rotor_wiring_with_str(setting.wiring.forward, setting.wiring.backward)
+                .is_err()
+            {
+                // the machine build such a rotor its own odd way, not copied here
+                tables.push(None);
+                continue;
+            }
+            let forward = Self::table(|idx| rotor.map_forward_index(idx));
+            let backward = Self::table(|idx| rotor.map_backward_index(idx));
+            tables.push(Some((forward, backward)));
+        }
+        let used_invalid = keys
+            .iter()
+            .flat_map(|key| key.rotor_order.iter())
+            .find(|&&idx| tables[idx].is_none());
+        if let Some(&idx) = used_invalid {
+            return Err(EnigmaKeyError::InvalidRotor(idx));
+        }
+
+        let count = keys.len();
+        let reflector = EnigmaReflector::new_with_str(reflector);
+        let mut ret = Self {
+            keys: count,
+            rotor_count,
+            offsets: vec![0; rotor_count * count],
+            rings: vec![0; rotor_count * count],
+            notches: vec![0; rotor_count * count],
+            forward: vec![0; rotor_count * count * 26],
+            backward: vec![0; rotor_count * count * 26],
+            plug_board: vec![0; count * 26],
+            reflector: Self::table(|idx| reflector.encode_index(idx)),
+            entry_forward: Self::table(|idx| idx),
+            entry_backward: Self::table(|idx| idx),
+        };
+
+        let letter = |s: &str, slot: usize| s.as_bytes().get(slot).map(|b| b - b'A');
+        for (k, key) in keys.iter().enumerate() {
+            for (slot, &idx) in key.rotor_order.iter().enumerate() {
+                let at = slot * count + k;
+                let setting = rotors[idx];
+                let (forward, backward) = tables[idx].unwrap();
+                // letters left out keep what the setting say, like `set_window`
+                ret.offsets[at] = letter(&key.window, slot).unwrap_or(setting.window as u8 - b'A');
+                ret.rings[at] = letter(&key.ring, slot).unwrap_or(0);
+                ret.notches[at] = (setting.notch as u8).wrapping_sub(b'A');
+                ret.forward[at * 26..at * 26 + 26].copy_from_slice(&forward);
+                ret.backward[at * 26..at * 26 + 26].copy_from_slice(&backward);
+            }
+
+            // clashing wires are left out, like `new_with_key`
+            let mut plug_board = EnigmaPlugBoard::new();
+            key.wires.iter().for_each(|&wire| {
+                let _ = plug_board.add_wire(wire);
+            });
+            ret.plug_board[k * 26..k * 26 + 26]
+                .copy_from_slice(&Self::table(|idx| plug_board.encode_index(idx)));
+        }
+        Ok(ret)
+    }
+
+    // keys in the order of the contacts they are wired to,
+    // see `EnigmaMachine::set_entry_wheel`
+    pub fn set_entry_wheel(&mut self, s: &str) {
+        let entry_wheel = EnigmaEntryWheel::new_with_str(s);
+        self.entry_forward = Self::table(|idx| entry_wheel.encode_forward_index(idx));
+        self.entry_backward = Self::table(|idx| entry_wheel.encode_backward_index(idx));
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys == 0
+    }
+
+    // decrypt of the text under every key, in the order the keys were given.
+    // the batch itself is not moved, it can be run again.
+    pub fn decrypt_all(&self, cipher_text: &str) -> Vec<String> {
+        if !is_enigma_text(cipher_text) {
+            return vec![String::new(); self.keys];
+        }
+        let len = cipher_text.len();
+        let n = self.keys;
+        // a machine with no rotor give the text back as it is
+        if self.rotor_count == 0 || len == 0 {
+            return vec![cipher_text.to_owned(); n];
+        }
+
+        let mut offsets = self.offsets.clone();
+        let mut carry = vec![false; n];
+        let mut signal = vec![0_u8; n];
+        let mut out = vec![0_u8; n * len];
+
+        for (pos, c) in cipher_text.bytes().enumerate() {
+            let c = (c - b'A') as usize;
+
+            // step: the first rotor always move, the next one only when the
+            // one before it was showing its notch, a greek wheel never
+            carry.iter_mut().for_each(|carry| *carry = true);
+            for slot in 0..self.rotor_count.min(EnigmaMachine::STEPPING_ROTORS) {
+                let offsets = &mut offsets[slot * n..slot * n + n];
+                let notches = &self.notches[slot * n..slot * n + n];
+                for k in 0..n {
+                    let at_notch = offsets[k] == notches[k];
+                    if carry[k] {
+                        offsets[k] = (offsets[k] + 1) % 26;
+                    }
+                    carry[k] = carry[k] && at_notch;
+                }
+            }
+
+            // plugboard and entry wheel
+            for (k, x) in signal.iter_mut().enumerate() {
+                *x = self.entry_forward[self.plug_board[k * 26 + c] as usize];
+            }
+            // rotors forward
+            for slot in 0..self.rotor_count {
+                self.through(slot, &offsets, &mut signal, &self.forward);
+            }
+            signal
+                .iter_mut()
+                .for_each(|x| *x = self.reflector[*x as usize]);
+            // rotors backward
+            for slot in (0..self.rotor_count).rev() {
+                self.through(slot, &offsets, &mut signal, &self.backward);
+            }
+            // entry wheel and plugboard again
+            for k in 0..n {
+                let x = self.entry_backward[signal[k] as usize] as usize;
+                out[k * len + pos] = self.plug_board[k * 26 + x] + b'A';
+            }
+        }
+
+        out.chunks(len)
+            .map(|letters| String::from_utf8(letters.to_vec()).unwrap())
+            .collect()
+    }
+
+    // fitness of the decrypt under every key
+    pub fn score_all(&self, cipher_text: &str, fitness: &dyn EnigmaFitness) -> Vec<f64> {
+        self.decrypt_all(cipher_text)
+            .iter()
+            .map(|text| fitness.score(text))
+            .collect()
+    }
+
+    // one rotor slot of every key, the same as `map_forward_index`
+    fn through(&self, slot: usize, offsets: &[u8], signal: &mut [u8], tables: &[u8]) {
+        let n = self.keys;
+        for (k, x) in signal.iter_mut().enumerate() {
+            let at = slot * n + k;
+            let shift = (offsets[at] + 26 - self.rings[at]) % 26;
+            let idx = (*x + shift) % 26;
+            *x = (tables[at * 26 + idx as usize] + 26 - shift) % 26;
+        }
+    }
+
+    fn table(f: impl Fn(usize) -> usize) -> [u8; 26] {
+        let mut ret = [0; 26];
+        ret.iter_mut()
+            .enumerate()
+            .for_each(|(idx, out)| *out = f(idx) as u8);
+        ret
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{enigma_machine::*, enigma_menu::*, enigma_types::*};
+
+// Turing-Welchman bombe.
+// a crib (guessed plain text) under the cipher text give pairs of letters
+// linked by the scrambler at that position. under a stecker S,
+//
+//     S(cipher) = E_i(S(crib))
+//
+// so a guess for the stecker partner of one letter imply partners for
+// every letter connected to it in the menu. the diagonal board add
+// S(a) = b  =>  S(b) = a. a position is a stop when some guess doesn't
+// lead to a letter having two different partners.
+// see `EnigmaMenu` for how the links are made and judged.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaBombeStop {
+    // index into the rotors given to the bombe
+    pub rotor_order: Vec<usize>,
+    // start position of the message
+    pub window: String,
+    // stecker partners implied by the stop
+    pub steckers: Vec<EnigmaPlugBoardWire>,
+    // menu letters that come out steckered to themselves
+    pub self_steckered: Vec<char>,
+}
+
+pub struct EnigmaBombe<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    links: Vec<EnigmaMenuLink>,
+    test_letter: usize,
+}
+
+impl<'a> EnigmaBombe<'a> {
+    // crib should sit under the cipher text from `offset`
+    pub fn new(
+        rotors: &[EnigmaRotorSetting<'a>],
+        reflector: &'a str,
+        cipher_text: &str,
+        crib: &str,
+        offset: usize,
+    ) -> Result<Self, EnigmaBombeError> {
+        let menu = EnigmaMenu::new(cipher_text, crib, offset)?;
+        Ok(Self::new_with_menu(rotors, reflector, &menu))
+    }
+
+    pub fn new_with_menu(
+        rotors: &[EnigmaRotorSetting<'a>],
+        reflector: &'a str,
+        menu: &EnigmaMenu,
+    ) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            links: menu.links().to_vec(),
+            test_letter: menu.test_letter(),
+        }
+    }
+
+    pub fn links(&self) -> &[EnigmaMenuLink] {
+        &self.links
+    }
+
+    // run every wheel order that can be made from the rotors given
+    pub fn run(&self, rotor_count: usize) -> Vec<EnigmaBombeStop> {
+        EnigmaRotorSetting::wheel_orders(self.rotors.len(), rotor_count)
+            .iter()
+            .flat_map(|order| self.run_wheel_order(order))
+            .collect()
+    }
+
+    // try every start position of one wheel order
+    pub fn run_wheel_order(&self, rotor_order: &[usize]) -> Vec<EnigmaBombeStop> {
+        let settings = rotor_order
+            .iter()
+            .map(|&idx| self.rotors[idx])
+            .collect::<Vec<EnigmaRotorSetting>>();
+        let mut machine = EnigmaMachine::new_with_all_setting(settings, self.reflector);
+
+        let last_position = self.links.iter().map(|l| l.position).max().unwrap_or(0);
+        let mut scramblers = vec![[0; 26]; last_position + 1];
+        let mut groups = EnigmaDiagonalBoard::new();
+
+        let mut ret = vec![];
+        for idx in 0..26_usize.pow(rotor_order.len() as u32) {
+            let window = EnigmaMachine::window_from_index(idx, rotor_order.len());
+            machine.set_window(&window);
+
+            // scrambler the machine use at every crib position,
+            // the machine step before each letter
+            let mut position = 0;
+            for link in self.links.iter() {
+                while position <= link.position {
+                    machine.step();
+                    position += 1;
+                }
+                scramblers[link.position] = machine.scramble_table();
+            }
+
+            if let Some(stops) = self.test_position(&scramblers, &mut groups) {
+                stops.into_iter().for_each(|(steckers, self_steckered)| {
+                    ret.push(EnigmaBombeStop {
+                        rotor_order: rotor_order.to_vec(),
+                        window: window.clone(),
+                        steckers,
+                        self_steckered,
+                    })
+                });
+            }
+        }
+        ret
+    }
+
+    // connect all the wires for one position, then look at the test register
+    fn test_position(
+        &self,
+        scramblers: &[[usize; 26]],
+        groups: &mut EnigmaDiagonalBoard,
+    ) -> Option<Vec<(Vec<EnigmaPlugBoardWire>, Vec<char>)>> {
+        groups.reset();
+        for link in self.links.iter() {
+            let scrambler = &scramblers[link.position];
+            for (x, &y) in scrambler.iter().enumerate() {
+                groups.union((link.crib, x), (link.cipher, y));
+            }
+        }
+
+        let mut ret = vec![];
+        for x in 0..26 {
+            let group = groups.find((self.test_letter, x));
+
+            // more than one live wire on the test register, guess contradict itself
+            let live = (0..26)
+                .filter(|&y| groups.find((self.test_letter, y)) == group)
+                .count();
+            if live != 1 {
+                continue;
+            }
+
+            // no letter in the group can have two partners
+            let mut partner = [None; 26];
+            let mut consistent = true;
+            'check: for a in 0..26 {
+                for b in a..26 {
+                    if groups.find((a, b)) != group {
+                        continue;
+                    }
+                    if partner[a].is_some_and(|p| p != b) || partner[b].is_some_and(|p| p != a) {
+                        consistent = false;
+                        break 'check;
+                    }
+                    partner[a] = Some(b);
+                    partner[b] = Some(a);
+                }
+            }
+            if !consistent {
+                continue;
+            }
+
+            let to_char = |idx: usize| (idx as u8 + b'A') as char;
+            let mut steckers = vec![];
+            let mut self_steckered = vec![];
+            partner.iter().enumerate().for_each(|(a, &p)| match p {
+                Some(b) if a == b => self_steckered.push(to_char(a)),
+                Some(b) if a < b => steckers.push(EnigmaPlugBoardWire(to_char(a), to_char(b))),
+                _ => {}
+            });
+            ret.push((steckers, self_steckered));
+        }
+
+        if ret.is_empty() {
+            None
+        } else {
+            Some(ret)
+        }
+    }
+}
+
+// 26 x 26 wires of the bombe, wire (a, b) means "a is steckered to b".
+// the diagonal board connect (a, b) and (b, a), so they share one slot.
+// wires that are connected are kept as groups (union find).
+struct EnigmaDiagonalBoard {
+    parent: Vec<usize>,
+}
+
+impl EnigmaDiagonalBoard {
+    fn new() -> Self {
+        Self {
+            parent: (0..26 * 26).collect(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.parent
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, p)| *p = idx);
+    }
+
+    fn slot(wire: (usize, usize)) -> usize {
+        wire.0.min(wire.1) * 26 + wire.0.max(wire.1)
+    }
+
+    fn find(&mut self, wire: (usize, usize)) -> usize {
+        let mut idx = Self::slot(wire);
+        while self.parent[idx] != idx {
+            self.parent[idx] = self.parent[self.parent[idx]];
+            idx = self.parent[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: (usize, usize), b: (usize, usize)) {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a != b {
+            self.parent[a] = b;
+        }
+    }
+}
@@ -13,6 +13,9 @@ pub struct EnigmaRotor {
     pre_rotor: Option<Rc<RefCell<EnigmaRotor>>>,
     next_rotor: Option<Rc<RefCell<EnigmaRotor>>>,
     offset: usize,
+    // ring setting (Ringstellung), turn the wiring against the letters
+    // on the ring, 0 is 'A'
+    ring: usize,
 }
 
 impl EnigmaRotor {
@@ -26,6 +29,7 @@ impl EnigmaRotor {
             pre_rotor: None,
             next_rotor: None,
             offset: 0,
+            ring: 0,
         }
     }
 
@@ -52,12 +56,31 @@ impl EnigmaRotor {
         ret
     }
 
+    // letter shown in the rotor window
+    pub fn get_window(&self) -> char {
+        self.window
+    }
+
+    pub fn get_notch(&self) -> char {
+        self.notch
+    }
+
     // set rotor window
     pub fn set_window(&mut self, c: char) {
         self.window = c;
         self.offset = (c as u8 - b'A') as usize;
     }
 
+    pub fn get_ring(&self) -> char {
+        (self.ring as u8 + b'A') as char
+    }
+
+    // set ring setting, the notch stay with the letters
+    // so only the wiring is moved
+    pub fn set_ring(&mut self, c: char) {
+        self.ring = (c as u8 - b'A') as usize;
+    }
+
     // 'link' to next rotor
     pub fn set_next_rotor(&mut self, rotor: Rc<RefCell<EnigmaRotor>>) {
         self.next_rotor = Some(rotor.clone());
@@ -126,20 +149,7 @@ impl EnigmaRotor {
     // encode a signal that pass in to the rotor
     // signal is covert to an index, for convenience.
     pub fn encode_forward_index(&self, idx: usize) -> usize {
-        // each rotor will rotate after condition is matched,
-        // this will create a mapping before signal enter rotor's wiring/mapping
-        // the mapping it create is represented with an offset.
-        let idx = idx as isize - self.offset as isize;
-        let idx = if idx < 0 {
-            (idx + 26) as usize
-        } else {
-            (idx % 26) as usize
-        };
-
-        // same as the moment enter rotor,
-        // there is also a mapping when siganl leave rotor
-        // so after covert we add it back
-        let ret_idx = (self.forward_mapping_array[idx] + self.offset) % 26;
+        let ret_idx = self.map_forward_index(idx);
 
         // if there is a next rotor, we pass the signal to the next,
         // continue the encoding process
@@ -153,20 +163,7 @@ impl EnigmaRotor {
     // encode a signal that pass in to the rotor
     // signal is covert to an index, for convenience.
     pub fn encode_backward_index(&self, idx: usize) -> usize {
-        // each rotor will rotate after condition is matched,
-        // this will create a mapping before signal enter rotor's wiring/mapping
-        // the mapping it create is represented with an offset.
-        let idx = idx as isize - self.offset as isize;
-        let idx = if idx < 0 {
-            (idx + 26) as usize
-        } else {
-            (idx % 26) as usize
-        };
-
-        // same as the moment enter rotor,
-        // there is also a mapping when siganl leave rotor
-        // so after covert we add it back
-        let ret_idx = (self.backward_mapping_array[idx] + self.offset) % 26;
+        let ret_idx = self.map_backward_index(idx);
 
         // if there is a previous rotor, we pass the signal to the previous,
         // continue the encoding process
@@ -177,6 +174,28 @@ impl EnigmaRotor {
         }
     }
 
+    // signal pass through this rotor only, forward direction
+    pub fn map_forward_index(&self, idx: usize) -> usize {
+        // each rotor will rotate after condition is matched,
+        // this will create a mapping before signal enter rotor's wiring/mapping
+        // the mapping it create is represented with an offset.
+        // the ring setting turn the wiring the other way.
+        let shift = (self.offset + 26 - self.ring) % 26;
+        let idx = (idx + shift) % 26;
+
+        // same as the moment enter rotor,
+        // there is also a mapping when siganl leave rotor
+        // so after covert we take it back
+        (self.forward_mapping_array[idx] + 26 - shift) % 26
+    }
+
+    // signal pass through this rotor only, backward direction
+    pub fn map_backward_index(&self, idx: usize) -> usize {
+        let shift = (self.offset + 26 - self.ring) % 26;
+        let idx = (idx + shift) % 26;
+        (self.backward_mapping_array[idx] + 26 - shift) % 26
+    }
+
     //  rotate the rotor
     pub fn rotate(&mut self) {
         if self.window == self.notch {
@@ -242,7 +261,7 @@ impl fmt::Display for EnigmaRotor {
             .iter()
             .map(|&u| u as u8 + b'A')
             .collect::<Vec<u8>>();
-        write!(fmt, "EnigmaRotor: {{\n\twindow: {},\n\tnotch: {},\n\toffset: {},\n\tforward: {},\n\tbackward: {}\n}}", self.window, self.notch, self.offset, str::from_utf8(f.as_slice()).unwrap(), str::from_utf8(b.as_slice()).unwrap())
+        write!(fmt, "EnigmaRotor: {{\n\twindow: {},\n\tnotch: {},\n\toffset: {},\n\tring: {},\n\tforward: {},\n\tbackward: {}\n}}", self.window, self.notch, self.offset, self.get_ring(), str::from_utf8(f.as_slice()).unwrap(), str::from_utf8(b.as_slice()).unwrap())
     }
 }
 
@@ -285,12 +304,98 @@ impl EnigmaPlugBoard {
         }
     }
 
+    // pull out the wire plugged in this charater, if there is one
+    pub fn remove_wire(&mut self, c: char) -> Option<EnigmaPlugBoardWire> {
+        let idx = (c as u8 - b'A') as usize;
+        let other = self.mapping_array[idx];
+        if other == idx {
+            None
+        } else {
+            self.mapping_array[idx] = idx;
+            self.mapping_array[other] = other;
+            Some(EnigmaPlugBoardWire(c, (other as u8 + b'A') as char))
+        }
+    }
+
+    // every wire plugged in, lower charater first
+    pub fn get_wires(&self) -> Vec<EnigmaPlugBoardWire> {
+        self.mapping_array
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &other)| idx < other)
+            .map(|(idx, &other)| {
+                EnigmaPlugBoardWire((idx as u8 + b'A') as char, (other as u8 + b'A') as char)
+            })
+            .collect()
+    }
+
     // swap two
     pub fn encode_index(&self, idx: usize) -> usize {
         self.mapping_array[idx]
     }
 }
 
+// entry wheel (Eintrittswalze) sit between the plugboard and the rotors.
+// military machines wire key A to contact A and so on,
+// the commercial ones follow the keyboard.
+pub struct EnigmaEntryWheel {
+    forward_mapping_array: Vec<usize>,
+    backward_mapping_array: Vec<usize>,
+}
+
+impl Default for EnigmaEntryWheel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnigmaEntryWheel {
+    // keys in the order of the contacts they are wired to
+    pub const ABC: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    pub const QWERTZU: &'static str = "QWERTZUIOASDFGHJKPYXCVBNML";
+
+    pub fn new() -> Self {
+        Self {
+            forward_mapping_array: (0..26_usize).collect::<Vec<usize>>(),
+            backward_mapping_array: (0..26_usize).collect::<Vec<usize>>(),
+        }
+    }
+
+    // lazy approach like rotor, if the keys are not every letter once
+    // the wheel is left wired straight through
+    pub fn new_with_str(keys: &str) -> Self {
+        let mut ret = Self::new();
+        let mut seen = [false; 26];
+        let is_vaild = keys.len() == 26
+            && keys.bytes().all(|b| {
+                let ok = b.is_ascii_uppercase() && !seen[(b - b'A') as usize];
+                if ok {
+                    seen[(b - b'A') as usize] = true;
+                }
+                ok
+            });
+
+        if is_vaild {
+            keys.bytes().enumerate().for_each(|(contact, b)| {
+                let key = (b - b'A') as usize;
+                ret.forward_mapping_array[key] = contact;
+                ret.backward_mapping_array[contact] = key;
+            });
+        }
+        ret
+    }
+
+    // key to contact
+    pub fn encode_forward_index(&self, idx: usize) -> usize {
+        self.forward_mapping_array[idx]
+    }
+
+    // contact to key
+    pub fn encode_backward_index(&self, idx: usize) -> usize {
+        self.backward_mapping_array[idx]
+    }
+}
+
 // reflector is place back in the last position in the machine
 // it basically connect two node in the last rotor,
 // so signal will send backward to the output through all rotor again,
@@ -0,0 +1,151 @@
+use crate::enigma_procedure::*;
+
+// enigma never encode a letter to itself (the reflector has no fixed point),
+// so a probable word cannot sit anywhere a letter of it is the same as
+// the cipher letter above it. sliding the word along the cipher text
+// leave only the offsets where nothing clash.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaCribPlacement {
+    pub crib: String,
+    pub offset: usize,
+    // rank of the dictionary the word come from, 0 is the most probable
+    pub dictionary: usize,
+}
+
+// probable words of one kind, e.g. weather reports
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaCribDictionary {
+    pub name: String,
+    pub words: Vec<String>,
+}
+
+impl EnigmaCribDictionary {
+    pub fn new(name: &str, words: &[&str]) -> Self {
+        Self {
+            name: name.to_owned(),
+            words: words.iter().map(|w| w.to_uppercase()).collect(),
+        }
+    }
+
+    // words that showed up again and again in real traffic
+    pub fn common() -> Vec<Self> {
+        vec![
+            Self::new(
+                "wetter",
+                &[
+                    "WETTERVORHERSAGE",
+                    "WETTERBERICHT",
+                    "WETTERLAGE",
+                    "BEWOELKUNG",
+                    "SIQTWEITE",
+                ],
+            ),
+            Self::new(
+                "routine",
+                &[
+                    "KEINEBESONDERENEREIGNISSE",
+                    "OBERKOMMANDODERWEHRMAQT",
+                    "FUEHRERHAUPTQUARTIER",
+                    "HEILHITLER",
+                ],
+            ),
+            Self::new("address", &["ANX", "VONX", "ANXOBERKOMMANDO"]),
+        ]
+    }
+}
+
+impl EnigmaCribPlacement {
+    // every offset where the crib doesn't clash with the cipher text
+    pub fn drag(cipher_text: &str, crib: &str) -> Vec<usize> {
+        if crib.is_empty() || crib.len() > cipher_text.len() {
+            return vec![];
+        }
+        if !is_enigma_text(cipher_text) || !is_enigma_text(crib) {
+            return vec![];
+        }
+
+        let cipher = cipher_text.as_bytes();
+        (0..=cipher.len() - crib.len())
+            .filter(|&offset| {
+                crib.bytes()
+                    .zip(cipher[offset..].iter())
+                    .all(|(p, &c)| p != c)
+            })
+            .collect()
+    }
+
+    // drag every word of every dictionary.
+    // placements of a better dictionary come first, then longer words,
+    // since a long word that fits is less likely to fit by chance.
+    pub fn rank(cipher_text: &str, dictionaries: &[EnigmaCribDictionary]) -> Vec<Self> {
+        let mut ret = vec![];
+        for (rank, dictionary) in dictionaries.iter().enumerate() {
+            for word in dictionary.words.iter() {
+                Self::drag(cipher_text, word)
+                    .into_iter()
+                    .for_each(|offset| {
+                        ret.push(Self {
+                            crib: word.clone(),
+                            offset,
+                            dictionary: rank,
+                        })
+                    });
+            }
+        }
+        ret.sort_by(|a, b| {
+            a.dictionary
+                .cmp(&b.dictionary)
+                .then(b.crib.len().cmp(&a.crib.len()))
+                .then(a.offset.cmp(&b.offset))
+        });
+        ret
+    }
+
+    // place several cribs at once.
+    // every crib must fit by itself, and where two of them overlap
+    // they must agree on the plain text.
+    pub fn combine(cipher_text: &str, cribs: &[&str]) -> Vec<Vec<Self>> {
+        let mut ret: Vec<Vec<Self>> = vec![vec![]];
+        for crib in cribs {
+            let offsets = Self::drag(cipher_text, crib);
+            ret = ret
+                .into_iter()
+                .flat_map(|placed| {
+                    offsets
+                        .iter()
+                        .filter(|&&offset| placed.iter().all(|p| Self::agree(p, crib, offset)))
+                        .map(|&offset| {
+                            let mut next = placed.clone();
+                            next.push(Self {
+                                crib: crib.to_string(),
+                                offset,
+                                dictionary: 0,
+                            });
+                            next
+                        })
+                        .collect::<Vec<Vec<Self>>>()
+                })
+                .collect();
+        }
+
+        ret.retain(|placed| !placed.is_empty());
+        ret
+    }
+
+    // plain text of a placement put over the cipher text
+    pub fn plain_text_at(&self, position: usize) -> Option<char> {
+        position
+            .checked_sub(self.offset)
+            .and_then(|idx| self.crib.as_bytes().get(idx))
+            .map(|&b| b as char)
+    }
+
+    fn agree(placed: &Self, crib: &str, offset: usize) -> bool {
+        crib.bytes().enumerate().all(|(idx, b)| {
+            placed
+                .plain_text_at(offset + idx)
+                .is_none_or(|c| c == b as char)
+        })
+    }
+}
@@ -0,0 +1,203 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{enigma_machine::*, enigma_procedure::*, enigma_types::*};
+
+// Rejewski's characteristic.
+// before 1938 the message key was typed twice at the day's ground setting,
+// so the 1st and 4th letter of every indicator come from the same letter.
+// call A to F the scramblers of the six positions, then AD send the 1st
+// letter to the 4th (A is its own inverse), same for BE and CF.
+// the plugboard only rename the letters of these products, so the length
+// of their cycles depend on the wheel order and position alone.
+// work them out for every setting once, then the indicators of a day
+// tell which few settings are left.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnigmaCycleCharacteristic {
+    // cycle lengths, longest first
+    pub ad: Vec<usize>,
+    pub be: Vec<usize>,
+    pub cf: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaCatalogEntry {
+    // index into the rotors given to the cyclometer
+    pub rotor_order: Vec<usize>,
+    // ground setting the indicators are typed at
+    pub window: String,
+}
+
+pub struct EnigmaCycleCatalog {
+    entries: HashMap<EnigmaCycleCharacteristic, Vec<EnigmaCatalogEntry>>,
+}
+
+pub struct EnigmaCyclometer<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+}
+
+impl EnigmaCycleCharacteristic {
+    // from the three products, each as a table of index
+    pub fn new_with_products(products: &[[usize; 26]; 3]) -> Self {
+        Self {
+            ad: Self::cycle_lengths(&products[0]),
+            be: Self::cycle_lengths(&products[1]),
+            cf: Self::cycle_lengths(&products[2]),
+        }
+    }
+
+    // from the doubled indicators intercepted in one day.
+    // every letter must show up at the first three places
+    // before the products are known.
+    pub fn from_indicators(indicators: &[&str]) -> Result<Self, EnigmaCyclometerError> {
+        let mut products = [[26; 26]; 3];
+        for (number, indicator) in indicators.iter().enumerate() {
+            if indicator.len() != 6 || !is_enigma_text(indicator) {
+                return Err(EnigmaCyclometerError::InvalidIndicator);
+            }
+
+            let letters = indicator
+                .bytes()
+                .map(|b| (b - b'A') as usize)
+                .collect::<Vec<usize>>();
+            for (i, product) in products.iter_mut().enumerate() {
+                let (from, to) = (letters[i], letters[i + 3]);
+                if product[from] != 26 && product[from] != to {
+                    return Err(EnigmaCyclometerError::Inconsistent(number));
+                }
+                product[from] = to;
+            }
+        }
+
+        if products.iter().flatten().any(|&to| to == 26) {
+            return Err(EnigmaCyclometerError::Incomplete);
+        }
+        Ok(Self::new_with_products(&products))
+    }
+
+    fn cycle_lengths(product: &[usize; 26]) -> Vec<usize> {
+        let mut seen = [false; 26];
+        let mut ret = vec![];
+        for start in 0..26 {
+            let mut len = 0;
+            let mut idx = start;
+            while !seen[idx] {
+                seen[idx] = true;
+                idx = product[idx];
+                len += 1;
+            }
+            if len > 0 {
+                ret.push(len);
+            }
+        }
+        ret.sort_by(|a, b| b.cmp(a));
+        ret
+    }
+}
+
+// e.g. `13 13 | 10 10 2 2 1 1 | 7 7 6 6`
+impl fmt::Display for EnigmaCycleCharacteristic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [&self.ad, &self.be, &self.cf]
+            .iter()
+            .map(|lengths| {
+                lengths
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>();
+        write!(f, "{}", parts.join(" | "))
+    }
+}
+
+impl EnigmaCycleCatalog {
+    // every setting with this characteristic
+    pub fn lookup(&self, characteristic: &EnigmaCycleCharacteristic) -> &[EnigmaCatalogEntry] {
+        self.entries
+            .get(characteristic)
+            .map(|entries| entries.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // how many different characteristics there are
+    pub fn characteristic_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    // how many settings were put in the catalog
+    pub fn setting_count(&self) -> usize {
+        self.entries.values().map(|entries| entries.len()).sum()
+    }
+}
+
+impl<'a> EnigmaCyclometer<'a> {
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>], reflector: &'a str) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+        }
+    }
+
+    // characteristic of one wheel order at one ground setting
+    pub fn characteristic(&self, rotor_order: &[usize], window: &str) -> EnigmaCycleCharacteristic {
+        let mut machine = self.machine(rotor_order);
+        machine.set_window(window);
+        Self::measure(&mut machine)
+    }
+
+    // the full catalog, every wheel order that can be made from the rotors given
+    pub fn catalog(&self, rotor_count: usize) -> EnigmaCycleCatalog {
+        let orders = EnigmaRotorSetting::wheel_orders(self.rotors.len(), rotor_count);
+        self.catalog_wheel_orders(&orders)
+    }
+
+    pub fn catalog_wheel_orders(&self, orders: &[Vec<usize>]) -> EnigmaCycleCatalog {
+        let mut entries: HashMap<EnigmaCycleCharacteristic, Vec<EnigmaCatalogEntry>> =
+            HashMap::new();
+        for order in orders {
+            let mut machine = self.machine(order);
+            for idx in 0..26_usize.pow(order.len() as u32) {
+                let window = EnigmaMachine::window_from_index(idx, order.len());
+                machine.set_window(&window);
+                entries
+                    .entry(Self::measure(&mut machine))
+                    .or_default()
+                    .push(EnigmaCatalogEntry {
+                        rotor_order: order.clone(),
+                        window,
+                    });
+            }
+        }
+        EnigmaCycleCatalog { entries }
+    }
+
+    // plugboard left empty
+    fn machine(&self, rotor_order: &[usize]) -> EnigmaMachine {
+        let settings = rotor_order
+            .iter()
+            .map(|&idx| self.rotors[idx])
+            .collect::<Vec<EnigmaRotorSetting>>();
+        EnigmaMachine::new_with_all_setting(settings, self.reflector)
+    }
+
+    fn measure(machine: &mut EnigmaMachine) -> EnigmaCycleCharacteristic {
+        let tables = (0..6)
+            .map(|_| {
+                machine.step();
+                machine.scramble_table()
+            })
+            .collect::<Vec<[usize; 26]>>();
+
+        let mut products = [[0; 26]; 3];
+        for (i, product) in products.iter_mut().enumerate() {
+            product
+                .iter_mut()
+                .enumerate()
+                .for_each(|(idx, p)| *p = tables[i + 3][tables[i][idx]]);
+        }
+        EnigmaCycleCharacteristic::new_with_products(&products)
+    }
+}
@@ -0,0 +1,157 @@
+use crate::enigma_procedure::*;
+
+// messages sent with the same key are "in depth": over the stretch they
+// share, the key is the same, so letters repeat between them as often as
+// between two plain texts (about 1 in 13 for german), not 1 in 26.
+// slide every two messages against each other and flag the alignments
+// where the repeats are too many to be chance. with thousands of
+// alignments tested some look good by luck, so the chance of each one is
+// multiplied by how many were tested before it is compared (Bonferroni).
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaDepth {
+    // index into the corpus
+    pub first: usize,
+    pub second: usize,
+    // where the shared stretch start in each message
+    pub first_start: usize,
+    pub second_start: usize,
+    pub overlap: usize,
+    pub coincidences: usize,
+    // standard deviations above what random letters give
+    pub sigma: f64,
+    // chance of this many coincidences or more from random letters
+    pub p_value: f64,
+    // the stretch of each message, one over the other
+    pub aligned: (String, String),
+}
+
+impl EnigmaDepth {
+    pub fn rate(&self) -> f64 {
+        self.coincidences as f64 / self.overlap as f64
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnigmaDepthSearch {
+    // alignments shorter than this are not tested
+    pub min_overlap: usize,
+    // chance of being wrong about any one of the depths flagged
+    pub significance: f64,
+}
+
+impl Default for EnigmaDepthSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnigmaDepthSearch {
+    pub fn new() -> Self {
+        Self {
+            min_overlap: 100,
+            significance: 0.01,
+        }
+    }
+
+    // every depth in the corpus, most significant first.
+    // cipher texts that are not 'A' to 'Z' only are skipped.
+    pub fn run(&self, corpus: &[&str]) -> Vec<EnigmaDepth> {
+        let mut pairs = vec![];
+        for first in 0..corpus.len() {
+            for second in first + 1..corpus.len() {
+                if is_enigma_text(corpus[first]) && is_enigma_text(corpus[second]) {
+                    pairs.push((first, second));
+                }
+            }
+        }
+
+        // count the alignments first, each one is then kept or dropped
+        // as soon as it is scored
+        let tests = pairs
+            .iter()
+            .map(|&(first, second)| {
+                self.starts(corpus[first].len(), corpus[second].len())
+                    .count()
+            })
+            .sum::<usize>() as f64;
+        let mut ret = pairs
+            .iter()
+            .flat_map(|&(first, second)| self.align(corpus, first, second, tests))
+            .collect::<Vec<EnigmaDepth>>();
+        ret.sort_by(|a, b| a.p_value.total_cmp(&b.p_value));
+        ret
+    }
+
+    // alignments of two messages that are significant out of `tests`
+    fn align(&self, corpus: &[&str], first: usize, second: usize, tests: f64) -> Vec<EnigmaDepth> {
+        let a = corpus[first].as_bytes();
+        let b = corpus[second].as_bytes();
+
+        self.starts(a.len(), b.len())
+            .filter_map(|(first_start, second_start, overlap)| {
+                let x = &a[first_start..first_start + overlap];
+                let y = &b[second_start..second_start + overlap];
+                let coincidences = x.iter().zip(y.iter()).filter(|(p, q)| p == q).count();
+
+                let p = 1.0 / 26.0;
+                let expected = overlap as f64 * p;
+                let sigma = (coincidences as f64 - expected) / (expected * (1.0 - p)).sqrt();
+                if sigma <= 0.0 {
+                    return None;
+                }
+                let p_value = Self::binomial_tail(overlap, coincidences);
+                if p_value * tests > self.significance {
+                    return None;
+                }
+                Some(EnigmaDepth {
+                    first,
+                    second,
+                    first_start,
+                    second_start,
+                    overlap,
+                    coincidences,
+                    sigma,
+                    p_value,
+                    aligned: (
+                        corpus[first][first_start..][..overlap].to_owned(),
+                        corpus[second][second_start..][..overlap].to_owned(),
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    // (first start, second start, overlap) of every alignment long enough
+    // to test, one of the two start at 0 and the other one is moved along
+    fn starts(&self, a: usize, b: usize) -> impl Iterator<Item = (usize, usize, usize)> {
+        let min_overlap = self.min_overlap.max(1);
+        (0..a)
+            .map(|start| (start, 0))
+            .chain((1..b).map(|start| (0, start)))
+            .map(move |(first_start, second_start)| {
+                let overlap = (a - first_start).min(b - second_start);
+                (first_start, second_start, overlap)
+            })
+            .filter(move |&(_, _, overlap)| overlap >= min_overlap)
+    }
+
+    // chance of `k` or more coincidences in `n` letters of random text
+    fn binomial_tail(n: usize, k: usize) -> f64 {
+        let p = 1.0_f64 / 26.0;
+        let q = 1.0 - p;
+        let log_choose = (0..k)
+            .map(|i| ((n - i) as f64 / (i + 1) as f64).ln())
+            .sum::<f64>();
+        let mut term = (log_choose + k as f64 * p.ln() + (n - k) as f64 * q.ln()).exp();
+        let mut sum = 0.0;
+        for i in k..=n {
+            sum += term;
+            term *= (n - i) as f64 / (i + 1) as f64 * p / q;
+            if term < sum * 1e-12 {
+                break;
+            }
+        }
+        sum.min(1.0)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::enigma_types::*;
+
+// traffic was sent in groups of letters,
+// five for army and air force, four for the navy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnigmaGroupFormat {
+    pub group_size: usize,
+    // 0 means everything in one line
+    pub groups_per_line: usize,
+}
+
+impl Default for EnigmaGroupFormat {
+    fn default() -> Self {
+        Self::HEER
+    }
+}
+
+impl EnigmaGroupFormat {
+    pub const HEER: EnigmaGroupFormat = EnigmaGroupFormat {
+        group_size: 5,
+        groups_per_line: 10,
+    };
+
+    pub const KRIEGSMARINE: EnigmaGroupFormat = EnigmaGroupFormat {
+        group_size: 4,
+        groups_per_line: 10,
+    };
+
+    // cut a text into groups, and groups into lines
+    pub fn format(&self, s: &str) -> String {
+        let group_size = self.group_size.max(1);
+        let groups = s
+            .as_bytes()
+            .chunks(group_size)
+            .map(|group| String::from_utf8_lossy(group).into_owned())
+            .collect::<Vec<String>>();
+
+        if self.groups_per_line == 0 {
+            groups.join(" ")
+        } else {
+            groups
+                .chunks(self.groups_per_line)
+                .map(|line| line.join(" "))
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+    }
+
+    // read grouped text back,
+    // any whitespace is dropped and lower case is accepted
+    pub fn parse(s: &str) -> Result<String, EnigmaMessageError> {
+        let text = s
+            .split_whitespace()
+            .collect::<String>()
+            .to_ascii_uppercase();
+        if text.bytes().all(|b| b.is_ascii_uppercase()) {
+            Ok(text)
+        } else {
+            Err(EnigmaMessageError::InvalidText)
+        }
+    }
+}
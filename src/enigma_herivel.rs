@@ -0,0 +1,204 @@
+use std::fmt;
+
+use crate::{enigma_machine::*, enigma_procedure::*, enigma_types::*};
+
+// mistakes of lazy operators.
+// Herivel tip: at the start of the day the rings were set and the rotors
+// put in, then the window show the ring setting. many operators picked
+// the ground setting of the first message by turning each rotor a few
+// clicks from there, so the first ground settings of the day bunch up
+// just after the ring setting.
+// each rotor is turned by hand on its own, a rotor going past its notch
+// doesn't move the next one, so the clicks of every rotor are counted
+// apart and turnovers are not looked at. an operator who moved the
+// rotors by typing would turn the middle rotor over too, such ground
+// settings are missed.
+// cillies: message keys that are easy to type (AAA, QWE, ABC), the same
+// as the ground setting, or simply where the rotors were left at the end
+// of the previous message.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaHerivelCandidate {
+    // fast rotor first, can be given to `set_ring` as it is
+    pub ring: String,
+    // ground settings that are a few clicks after this ring setting
+    pub hits: usize,
+    // clicks from the ring setting to those ground settings, added up
+    pub clicks: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnigmaHerivelTip {
+    // most clicks an operator turned a rotor from the ring setting
+    pub spread: usize,
+    pub top_n: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnigmaCillyKind {
+    // same letter again, e.g. AAA
+    Repeated,
+    // next to each other in the alphabet, e.g. ABC or ZYX
+    Alphabet,
+    // next to each other on the keyboard, e.g. QWE or LMN
+    Keyboard,
+    // message key is the ground setting
+    SameAsGround,
+    // message key is where an earlier message left the rotors
+    PreviousPosition(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaCilly {
+    // index into the messages given
+    pub message: usize,
+    // fast rotor first, like the window
+    pub message_key: String,
+    pub kinds: Vec<EnigmaCillyKind>,
+}
+
+impl fmt::Display for EnigmaCillyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Repeated => write!(f, "repeated"),
+            Self::Alphabet => write!(f, "alphabet"),
+            Self::Keyboard => write!(f, "keyboard"),
+            Self::SameAsGround => write!(f, "same as ground"),
+            Self::PreviousPosition(message) => write!(f, "end of message {}", message),
+        }
+    }
+}
+
+impl Default for EnigmaHerivelTip {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnigmaHerivelTip {
+    pub fn new() -> Self {
+        Self {
+            spread: 5,
+            top_n: 10,
+        }
+    }
+
+    // ring settings the first ground settings of the day point to,
+    // most hits first, then the fewest clicks
+    pub fn run(&self, first_headers: &[EnigmaMessageHeader]) -> Vec<EnigmaHerivelCandidate> {
+        let grounds = first_headers
+            .iter()
+            .map(|header| header.grundstellung.as_str())
+            .filter(|ground| is_enigma_text(ground))
+            .collect::<Vec<&str>>();
+        let rotor_count = match grounds.first() {
+            Some(ground) => ground.len(),
+            None => return vec![],
+        };
+
+        let mut ret = (0..26_usize.pow(rotor_count as u32))
+            .map(|idx| {
+                let ring = EnigmaMachine::window_from_index(idx, rotor_count);
+                let (hits, clicks) = grounds
+                    .iter()
+                    .filter_map(|ground| self.clicks(&ring, ground))
+                    .fold((0, 0), |(hits, clicks), c| (hits + 1, clicks + c));
+                EnigmaHerivelCandidate { ring, hits, clicks }
+            })
+            .filter(|candidate| candidate.hits > 0)
+            .collect::<Vec<EnigmaHerivelCandidate>>();
+        ret.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.clicks.cmp(&b.clicks)));
+        ret.truncate(self.top_n);
+        ret
+    }
+
+    // clicks from the ring setting to the ground setting,
+    // if every rotor is within the spread. no turnover, see above
+    fn clicks(&self, ring: &str, ground: &str) -> Option<usize> {
+        if ring.len() != ground.len() {
+            return None;
+        }
+        ring.bytes().zip(ground.bytes()).try_fold(0, |acc, (r, g)| {
+            let clicks = (g as usize + 26 - r as usize) % 26;
+            (clicks <= self.spread).then_some(acc + clicks)
+        })
+    }
+}
+
+impl EnigmaCilly {
+    // rows of the german keyboard
+    const KEYBOARD: [&'static str; 3] = ["QWERTZUIO", "ASDFGHJK", "PYXCVBNML"];
+
+    // message keys of the day that look chosen by a lazy operator.
+    // the keys are deciphered with the machine, so it need the right
+    // wheel order, ring setting and plugboard (or a guess of them).
+    pub fn find(
+        machine: &mut EnigmaMachine,
+        messages: &[EnigmaMessage],
+    ) -> Result<Vec<Self>, EnigmaMessageError> {
+        let keys = messages
+            .iter()
+            .map(|message| message.decode_message_key(machine))
+            .collect::<Result<Vec<String>, EnigmaMessageError>>()?;
+
+        // where the rotors are left after each message, stepped the way
+        // the machine does it, middle rotor turnover included
+        let ends = messages
+            .iter()
+            .zip(keys.iter())
+            .map(|(message, key)| {
+                machine.set_window(key);
+                (0..message.body.len()).for_each(|_| machine.step());
+                machine.get_window()
+            })
+            .collect::<Vec<String>>();
+
+        let mut ret = vec![];
+        for (idx, (message, key)) in messages.iter().zip(keys.iter()).enumerate() {
+            let mut kinds = Self::patterns(key);
+            if *key == message.header.grundstellung {
+                kinds.push(EnigmaCillyKind::SameAsGround);
+            }
+            if let Some(previous) = ends[..idx].iter().position(|end| end == key) {
+                kinds.push(EnigmaCillyKind::PreviousPosition(previous));
+            }
+            if !kinds.is_empty() {
+                ret.push(Self {
+                    message: idx,
+                    message_key: key.clone(),
+                    kinds,
+                });
+            }
+        }
+        Ok(ret)
+    }
+
+    // patterns that can be told from the key alone, they read the same
+    // both ways, so it can be a window or the key as typed
+    pub fn patterns(key: &str) -> Vec<EnigmaCillyKind> {
+        let mut ret = vec![];
+        let letters = key.as_bytes();
+        if letters.len() < 2 {
+            return ret;
+        }
+
+        if letters.iter().all(|&b| b == letters[0]) {
+            ret.push(EnigmaCillyKind::Repeated);
+        }
+
+        let step = |a: u8, b: u8| (b as i32 - a as i32 + 26) % 26;
+        let first = step(letters[0], letters[1]);
+        if (first == 1 || first == 25) && letters.windows(2).all(|w| step(w[0], w[1]) == first) {
+            ret.push(EnigmaCillyKind::Alphabet);
+        }
+
+        let on_keyboard = Self::KEYBOARD.iter().any(|row| {
+            let reversed = row.chars().rev().collect::<String>();
+            row.contains(key) || reversed.contains(key)
+        });
+        if on_keyboard {
+            ret.push(EnigmaCillyKind::Keyboard);
+        }
+        ret
+    }
+}
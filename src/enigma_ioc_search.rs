@@ -0,0 +1,83 @@
+use crate::{
+    enigma_machine::*, enigma_procedure::*, enigma_stats::*, enigma_top::*, enigma_types::*,
+};
+
+// ciphertext-only attack (Gillogly).
+// with the plugboard left empty, the decrypt under the right wheel order
+// and start position is still closer to plain text than any other,
+// which show up as a higher index of coincidence.
+// the best candidates are then handed to the plugboard recovery.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaIocCandidate {
+    // index into the rotors given to the search
+    pub rotor_order: Vec<usize>,
+    pub window: String,
+    pub score: f64,
+}
+
+pub struct EnigmaIocSearch<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    top_n: usize,
+}
+
+impl<'a> EnigmaIocSearch<'a> {
+    // keep the `top_n` best candidates
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>], reflector: &'a str, top_n: usize) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            top_n,
+        }
+    }
+
+    // try every wheel order that can be made from the rotors given
+    pub fn run(&self, cipher_text: &str, rotor_count: usize) -> Vec<EnigmaIocCandidate> {
+        let orders = EnigmaRotorSetting::wheel_orders(self.rotors.len(), rotor_count);
+        self.run_wheel_orders(cipher_text, &orders)
+    }
+
+    pub fn run_wheel_orders(
+        &self,
+        cipher_text: &str,
+        orders: &[Vec<usize>],
+    ) -> Vec<EnigmaIocCandidate> {
+        let mut ret = vec![];
+        if !is_enigma_text(cipher_text) {
+            return ret;
+        }
+
+        for order in orders {
+            let settings = order
+                .iter()
+                .map(|&idx| self.rotors[idx])
+                .collect::<Vec<EnigmaRotorSetting>>();
+            let mut machine = EnigmaMachine::new_with_all_setting(settings, self.reflector);
+
+            for idx in 0..26_usize.pow(order.len() as u32) {
+                let window = EnigmaMachine::window_from_index(idx, order.len());
+                machine.set_window(&window);
+
+                // count letters as they come out, no need to build the string
+                let mut counts = [0; 26];
+                cipher_text.chars().for_each(|c| {
+                    counts[(machine.encode_charater(c) as u8 - b'A') as usize] += 1;
+                });
+                let score = index_of_coincidence_counts(&counts);
+
+                keep_top(
+                    &mut ret,
+                    EnigmaIocCandidate {
+                        rotor_order: order.clone(),
+                        window,
+                        score,
+                    },
+                    self.top_n,
+                    |c| c.score,
+                );
+            }
+        }
+        ret
+    }
+}
@@ -2,18 +2,110 @@ use std::{cell::RefCell, fmt, rc::Rc, str};
 
 use crate::{enigma_types::*, enigma_component::*};
 
+// what happened to the signal during one keypress.
+// rotor related fields follow the order rotors are given to the machine,
+// except `rotors_backward` which is the order the signal come back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaKeyTrace {
+    pub input: char,
+    pub window_before: String,
+    pub window_after: String,
+    pub stepped: Vec<bool>,
+    // letter after the plugboard on the way in
+    pub plugboard_in: char,
+    // letter after each rotor, forward
+    pub rotors_forward: Vec<char>,
+    pub reflector: char,
+    // letter after each rotor, backward
+    pub rotors_backward: Vec<char>,
+    pub output: char,
+}
+
+// everything that change from day to day or message to message,
+// e.g. `0,3,1 BQE NXF AT CL HW`.
+// rotors are index into a list of rotors kept by whoever use the key.
+// order, ring and window are fast rotor first, like every key in the crate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaKey {
+    pub rotor_order: Vec<usize>,
+    pub ring: String,
+    pub window: String,
+    pub wires: Vec<EnigmaPlugBoardWire>,
+}
+
+impl EnigmaKey {
+    // back from the `Display` form
+    pub fn parse(s: &str) -> Result<Self, EnigmaKeyError> {
+        let mut parts = s.split_whitespace();
+        let rotor_order = parts
+            .next()
+            .ok_or(EnigmaKeyError::InvalidRotorOrder)?
+            .split(',')
+            .map(|idx| idx.parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| EnigmaKeyError::InvalidRotorOrder)?;
+
+        let letters = |part: Option<&str>| match part {
+            Some(p)
+                if p.len() == rotor_order.len() && p.bytes().all(|b| b.is_ascii_uppercase()) =>
+            {
+                Ok(p.to_owned())
+            }
+            _ => Err(EnigmaKeyError::InvalidLetters),
+        };
+        let ring = letters(parts.next())?;
+        let window = letters(parts.next())?;
+
+        let wires = parts
+            .map(|wire| match wire.as_bytes() {
+                &[a, b] if a.is_ascii_uppercase() && b.is_ascii_uppercase() && a != b => {
+                    Ok(EnigmaPlugBoardWire(a as char, b as char))
+                }
+                _ => Err(EnigmaKeyError::InvalidWire),
+            })
+            .collect::<Result<Vec<EnigmaPlugBoardWire>, EnigmaKeyError>>()?;
+
+        Ok(Self {
+            rotor_order,
+            ring,
+            window,
+            wires,
+        })
+    }
+}
+
+impl fmt::Display for EnigmaKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let order = self
+            .rotor_order
+            .iter()
+            .map(|idx| idx.to_string())
+            .collect::<Vec<String>>();
+        write!(f, "{} {} {}", order.join(","), self.ring, self.window)?;
+        for wire in self.wires.iter() {
+            write!(f, " {}{}", wire.0, wire.1)?;
+        }
+        Ok(())
+    }
+}
+
 pub struct EnigmaMachine {
     rotors: Vec<Rc<RefCell<EnigmaRotor>>>,
     reflector: EnigmaReflector,
     plug_board: EnigmaPlugBoard,
+    entry_wheel: EnigmaEntryWheel,
 }
 
 impl EnigmaMachine {
+    // fast, middle and slow rotor, any rotor after them stay where it is set
+    pub const STEPPING_ROTORS: usize = 3;
+
     fn new_with_reflector_and_empty_rotors(reflector_setting: &str) -> Self {
         Self {
             rotors: vec![],
             reflector: EnigmaReflector::new_with_str(reflector_setting),
             plug_board: EnigmaPlugBoard::new(),
+            entry_wheel: EnigmaEntryWheel::new(),
         }
     }
 
@@ -47,11 +139,43 @@ impl EnigmaMachine {
         ret
     }
 
+    // machine for a key, `rotors` is the list the key's rotor order index into.
+    // wires that clash with one already plugged are left out.
+    pub fn new_with_key(
+        rotors: &[EnigmaRotorSetting],
+        reflector_setting_str: &str,
+        key: &EnigmaKey,
+    ) -> Result<Self, EnigmaKeyError> {
+        let settings = key
+            .rotor_order
+            .iter()
+            .map(|&idx| rotors.get(idx).copied().ok_or(EnigmaKeyError::InvalidRotor(idx)))
+            .collect::<Result<Vec<EnigmaRotorSetting>, EnigmaKeyError>>()?;
+        let mut ret = Self::new_with_all_setting(settings, reflector_setting_str);
+        ret.set_ring(&key.ring);
+        ret.set_window(&key.window);
+        key.wires.iter().for_each(|&wire| {
+            let _ = ret.add_plug_wire(wire);
+        });
+        Ok(ret)
+    }
+
+    // how many rotors are chained in the machine
+    pub fn rotor_count(&self) -> usize {
+        self.rotors.len()
+    }
+
     // set up reflector
     pub fn set_reflector(&mut self, s: &str) {
         self.reflector.set_reflect_with_str(s);
     }
 
+    // keys in the order of the contacts they are wired to,
+    // e.g. `EnigmaEntryWheel::QWERTZU` for commercial machines
+    pub fn set_entry_wheel(&mut self, s: &str) {
+        self.entry_wheel = EnigmaEntryWheel::new_with_str(s);
+    }
+
     // each rotor can be set it's start up position
     // given it a fixed offset
     pub fn set_window(&mut self, s: &str) {
@@ -63,6 +187,40 @@ impl EnigmaMachine {
             })
     }
 
+    // current start up position, one letter for each rotor
+    pub fn get_window(&self) -> String {
+        self.rotors
+            .iter()
+            .map(|rc_rotor| rc_rotor.borrow().get_window())
+            .collect::<String>()
+    }
+
+    // ring setting of each rotor, one letter for each rotor like the window
+    pub fn set_ring(&mut self, s: &str) {
+        self.rotors
+            .iter()
+            .zip(s.chars())
+            .for_each(|(rc_rotor, ring)| {
+                rc_rotor.borrow_mut().set_ring(ring);
+            })
+    }
+
+    pub fn get_ring(&self) -> String {
+        self.rotors
+            .iter()
+            .map(|rc_rotor| rc_rotor.borrow().get_ring())
+            .collect::<String>()
+    }
+
+    // every start up position can be numbered from 0 to 26^rotor_count - 1,
+    // first rotor's window change fastest
+    pub fn window_from_index(idx: usize, rotor_count: usize) -> String {
+        (0..rotor_count)
+            .map(|i| ((idx / 26_usize.pow(i as u32)) % 26) as u8 + b'A')
+            .map(|b| b as char)
+            .collect::<String>()
+    }
+
     // add swap charater in plugboard
     pub fn add_plug_wire(
         &mut self,
@@ -71,6 +229,20 @@ impl EnigmaMachine {
         self.plug_board.add_wire(wire)
     }
 
+    // pull out the wire plugged in this charater
+    pub fn remove_plug_wire(&mut self, c: char) -> Option<EnigmaPlugBoardWire> {
+        self.plug_board.remove_wire(c)
+    }
+
+    // pull out every wire
+    pub fn clear_plug_wires(&mut self) {
+        self.plug_board = EnigmaPlugBoard::new();
+    }
+
+    pub fn get_plug_wires(&self) -> Vec<EnigmaPlugBoardWire> {
+        self.plug_board.get_wires()
+    }
+
     // encode process
     pub fn encode_charater(&mut self, c: char) -> char {
         if self.rotors.len() == 0 {
@@ -81,28 +253,152 @@ impl EnigmaMachine {
             let char_in_usize = (c as u8 - b'A') as usize;
     
             // rotate all rotor(if needed)
-            self.rotors[0].borrow_mut().rotate();
+            self.step();
     
             // convert in plugboard
             let step1 = self.plug_board.encode_index(char_in_usize);
     
-            // encode in rotors
-            let step2 = self.rotors[0].borrow().encode_forward_index(step1);
-    
-            // in reflector
-            let reflect_index = self.reflector.encode_index(step2);
-    
-            // encode in rotors (backward)
-            let last_rotor = self.rotors.last().unwrap().borrow();
-            let step3 = last_rotor.encode_backward_index(reflect_index);
+            // encode in rotors, reflector and back through rotors
+            let step2 = self.scramble_index(step1);
     
             // finally in plugboard
-            let last = self.plug_board.encode_index(step3);
+            let last = self.plug_board.encode_index(step2);
     
             return (last as u8 + b'A') as char;
         }
     }
 
+    // rotate rotors the same way a keypress does, without encoding anything.
+    // a rotor push the next one on when it leave its notch, but only the
+    // first `STEPPING_ROTORS` have a pawl, the greek wheel of the M4 never move.
+    pub fn step(&mut self) {
+        for rc_rotor in self.rotors.iter().take(Self::STEPPING_ROTORS) {
+            let mut rotor = rc_rotor.borrow_mut();
+            let window = rotor.get_window();
+            let turnover = window == rotor.get_notch();
+            rotor.set_window(((window as u8 - b'A' + 1) % 26 + b'A') as char);
+            if !turnover {
+                break;
+            }
+        }
+    }
+
+    // signal path through entry wheel, rotors and reflector at current position.
+    // plugboard is not included and rotors don't move.
+    pub fn scramble_index(&self, idx: usize) -> usize {
+        if self.rotors.is_empty() {
+            return idx;
+        }
+
+        // encode in rotors
+        let idx = self.entry_wheel.encode_forward_index(idx);
+        let step1 = self.rotors[0].borrow().encode_forward_index(idx);
+
+        // in reflector
+        let reflect_index = self.reflector.encode_index(step1);
+
+        // encode in rotors (backward)
+        let last_rotor = self.rotors.last().unwrap().borrow();
+        let last = last_rotor.encode_backward_index(reflect_index);
+        self.entry_wheel.encode_backward_index(last)
+    }
+
+    // contact the signal reach the reflector at, from the entry wheel
+    // through the rotors at current position. plugboard is not included.
+    pub fn reflector_side_index(&self, idx: usize) -> usize {
+        match self.rotors.first() {
+            Some(rc_rotor) => rc_rotor
+                .borrow()
+                .encode_forward_index(self.entry_wheel.encode_forward_index(idx)),
+            None => idx,
+        }
+    }
+
+    // whole scrambler at current position, index in and index out
+    pub fn scramble_table(&self) -> [usize; 26] {
+        let mut ret = [0; 26];
+        ret.iter_mut()
+            .enumerate()
+            .for_each(|(idx, out)| *out = self.scramble_index(idx));
+        ret
+    }
+
+    // same as `encode_charater`, but record every stage the signal pass
+    pub fn encode_traced(&mut self, c: char) -> EnigmaKeyTrace {
+        let window_before = self.get_window();
+        let to_char = |idx: usize| (idx as u8 + b'A') as char;
+
+        if self.rotors.is_empty() {
+            return EnigmaKeyTrace {
+                input: c,
+                window_after: window_before.clone(),
+                window_before,
+                stepped: vec![],
+                plugboard_in: c,
+                rotors_forward: vec![],
+                reflector: c,
+                rotors_backward: vec![],
+                output: c,
+            };
+        }
+
+        let char_in_usize = (c as u8 - b'A') as usize;
+        self.step();
+        let window_after = self.get_window();
+        let stepped = window_before
+            .chars()
+            .zip(window_after.chars())
+            .map(|(b, a)| b != a)
+            .collect::<Vec<bool>>();
+
+        let mut idx = self.plug_board.encode_index(char_in_usize);
+        let plugboard_in = to_char(idx);
+
+        // go through rotor one by one instead of the chained call
+        idx = self.entry_wheel.encode_forward_index(idx);
+        let rotors_forward = self
+            .rotors
+            .iter()
+            .map(|rc_rotor| {
+                idx = rc_rotor.borrow().map_forward_index(idx);
+                to_char(idx)
+            })
+            .collect::<Vec<char>>();
+
+        idx = self.reflector.encode_index(idx);
+        let reflector = to_char(idx);
+
+        let rotors_backward = self
+            .rotors
+            .iter()
+            .rev()
+            .map(|rc_rotor| {
+                idx = rc_rotor.borrow().map_backward_index(idx);
+                to_char(idx)
+            })
+            .collect::<Vec<char>>();
+
+        idx = self.entry_wheel.encode_backward_index(idx);
+        let output = to_char(self.plug_board.encode_index(idx));
+
+        EnigmaKeyTrace {
+            input: c,
+            window_before,
+            window_after,
+            stepped,
+            plugboard_in,
+            rotors_forward,
+            reflector,
+            rotors_backward,
+            output,
+        }
+    }
+
+    // trace every charater of a str
+    pub fn encode_str_traced(&mut self, s: &str) -> Vec<EnigmaKeyTrace> {
+        s.chars().map(|c| self.encode_traced(c)).collect()
+    }
+
     // same but will encode a str
     pub fn encode_str(&mut self, s: &str) -> String {
         let v8: Vec<u8> = s
@@ -130,3 +426,21 @@ impl fmt::Display for EnigmaMachine {
         )
     }
 }
+
+// e.g. `PAA -> QAA  I > I > MWU > C > GRZ > Z`
+impl fmt::Display for EnigmaKeyTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {}  {} > {} > {} > {} > {} > {}",
+            self.window_before,
+            self.window_after,
+            self.input,
+            self.plugboard_in,
+            self.rotors_forward.iter().collect::<String>(),
+            self.reflector,
+            self.rotors_backward.iter().collect::<String>(),
+            self.output
+        )
+    }
+}
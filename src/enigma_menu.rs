@@ -0,0 +1,200 @@
+use std::fmt::Write;
+
+use crate::{enigma_procedure::*, enigma_types::*};
+
+// menu of a crib, a graph where every letter is a node and every
+// crib/cipher pair is a link, labelled with the position it came from.
+// a closure (loop) in the menu is what let the bombe reject a position,
+// so the more closures, the less false stops.
+
+// one link of the menu, crib letter and cipher letter at `position`,
+// position count from the start of the message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnigmaMenuLink {
+    pub crib: usize,
+    pub cipher: usize,
+    pub position: usize,
+}
+
+// letters that are connected to each other
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaMenuComponent {
+    pub letters: Vec<usize>,
+    pub links: usize,
+    pub closures: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaMenu {
+    links: Vec<EnigmaMenuLink>,
+}
+
+impl EnigmaMenu {
+    // crib should sit under the cipher text from `offset`
+    pub fn new(cipher_text: &str, crib: &str, offset: usize) -> Result<Self, EnigmaBombeError> {
+        if !is_enigma_text(cipher_text) || !is_enigma_text(crib) || crib.is_empty() {
+            return Err(EnigmaBombeError::InvalidText);
+        }
+        if offset + crib.len() > cipher_text.len() {
+            return Err(EnigmaBombeError::CribOutOfRange);
+        }
+
+        let cipher = &cipher_text.as_bytes()[offset..offset + crib.len()];
+        let mut links = vec![];
+        for (idx, (&p, &c)) in crib.as_bytes().iter().zip(cipher.iter()).enumerate() {
+            // enigma never encode a letter to itself
+            if p == c {
+                return Err(EnigmaBombeError::CribClash(offset + idx));
+            }
+            links.push(EnigmaMenuLink {
+                crib: (p - b'A') as usize,
+                cipher: (c - b'A') as usize,
+                position: offset + idx,
+            });
+        }
+
+        Ok(Self { links })
+    }
+
+    // try the crib at every offset, best menu first.
+    // offsets where the crib clash with the cipher text are left out.
+    pub fn rank_offsets(cipher_text: &str, crib: &str) -> Vec<(usize, Self)> {
+        let mut ret = (0..=cipher_text.len().saturating_sub(crib.len()))
+            .filter_map(|offset| {
+                Self::new(cipher_text, crib, offset)
+                    .ok()
+                    .map(|m| (offset, m))
+            })
+            .collect::<Vec<(usize, Self)>>();
+        ret.sort_by(|a, b| {
+            let a = a.1.main_component();
+            let b = b.1.main_component();
+            b.closures.cmp(&a.closures).then(b.links.cmp(&a.links))
+        });
+        ret
+    }
+
+    pub fn links(&self) -> &[EnigmaMenuLink] {
+        &self.links
+    }
+
+    // how many links each letter have
+    pub fn degrees(&self) -> [usize; 26] {
+        let mut ret = [0; 26];
+        self.links.iter().for_each(|link| {
+            ret[link.crib] += 1;
+            ret[link.cipher] += 1;
+        });
+        ret
+    }
+
+    // letter with most links, the bombe's test register is put there
+    pub fn test_letter(&self) -> usize {
+        let degree = self.degrees();
+        let main = self.main_component();
+        main.letters
+            .iter()
+            .copied()
+            .max_by_key(|&idx| (degree[idx], std::cmp::Reverse(idx)))
+            .unwrap_or(0)
+    }
+
+    // split the menu into connected parts, the biggest one first.
+    // closures of a part is links - letters + 1 (its cycle rank).
+    pub fn components(&self) -> Vec<EnigmaMenuComponent> {
+        let mut parent = (0..26).collect::<Vec<usize>>();
+        fn find(parent: &mut [usize], mut idx: usize) -> usize {
+            while parent[idx] != idx {
+                parent[idx] = parent[parent[idx]];
+                idx = parent[idx];
+            }
+            idx
+        }
+        self.links.iter().for_each(|link| {
+            let a = find(&mut parent, link.crib);
+            let b = find(&mut parent, link.cipher);
+            parent[a] = b;
+        });
+
+        let degree = self.degrees();
+        let mut ret = vec![];
+        for root in 0..26 {
+            let letters = (0..26)
+                .filter(|&idx| degree[idx] > 0 && find(&mut parent, idx) == root)
+                .collect::<Vec<usize>>();
+            if letters.is_empty() {
+                continue;
+            }
+            let links = self
+                .links
+                .iter()
+                .filter(|link| find(&mut parent, link.crib) == root)
+                .count();
+            ret.push(EnigmaMenuComponent {
+                closures: links + 1 - letters.len(),
+                letters,
+                links,
+            });
+        }
+        ret.sort_by(|a, b| b.links.cmp(&a.links).then(b.closures.cmp(&a.closures)));
+        ret
+    }
+
+    // part of the menu the bombe actually test
+    pub fn main_component(&self) -> EnigmaMenuComponent {
+        self.components()
+            .into_iter()
+            .next()
+            .unwrap_or(EnigmaMenuComponent {
+                letters: vec![],
+                links: 0,
+                closures: 0,
+            })
+    }
+
+    pub fn closures(&self) -> usize {
+        self.components().iter().map(|c| c.closures).sum()
+    }
+
+    // rough number of false stops for one wheel order.
+    // each of the 26 guesses at each position survive a closure
+    // with chance 1/26, so
+    //
+    //     stops = 26^rotor_count * 26 / 26^closures
+    //
+    // the diagonal board and the letters hanging off the loops cut it
+    // further, so this is on the high side.
+    pub fn expected_stops(&self, rotor_count: usize) -> f64 {
+        let closures = self.main_component().closures as i32;
+        26_f64.powi(rotor_count as i32 + 1 - closures)
+    }
+
+    // graphviz dot, test letter drawn with a double circle
+    pub fn to_dot(&self) -> String {
+        let to_char = |idx: usize| (idx as u8 + b'A') as char;
+        let degree = self.degrees();
+
+        let mut ret = String::from("graph menu {\n");
+        let _ = writeln!(
+            ret,
+            "    {} [shape=doublecircle];",
+            to_char(self.test_letter())
+        );
+        (0..26).filter(|&idx| degree[idx] > 0).for_each(|idx| {
+            if idx != self.test_letter() {
+                let _ = writeln!(ret, "    {} [shape=circle];", to_char(idx));
+            }
+        });
+        self.links.iter().for_each(|link| {
+            let _ = writeln!(
+                ret,
+                "    {} -- {} [label=\"{}\"];",
+                to_char(link.crib),
+                to_char(link.cipher),
+                link.position
+            );
+        });
+        ret.push_str("}\n");
+        ret
+    }
+}
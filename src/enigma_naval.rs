@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::{enigma_machine::*, enigma_procedure::*, enigma_types::*};
+
+// Kriegsmarine indicator procedure.
+// two trigrams are taken from the kenngruppenbuch,
+// the first identifies the key, the second is enciphered at the grundstellung
+// to become the message key.
+// both are written one above the other with a filler letter,
+//
+//     X S W Q
+//     R A F Y
+//
+// and the vertical pairs are disguised with a bigram table
+// (doppelbuchstabentauschtafel) before they are sent as two four letter groups.
+// the grundstellung is a window, fast rotor first, the trigrams are letters
+// as typed.
+// on the four rotor M4 the trigrams still set only the three rotors next to
+// the entry, the greek wheel stay where the grundstellung put it.
+
+// reciprocal substitution of letter pairs,
+// if AB becomes CD then CD becomes AB.
+pub struct EnigmaBigramTable {
+    mapping_array: Vec<Option<usize>>,
+}
+
+impl Default for EnigmaBigramTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnigmaBigramTable {
+    pub fn new() -> Self {
+        Self {
+            mapping_array: vec![None; 26 * 26],
+        }
+    }
+
+    pub fn new_with_pairs(pairs: &[(&str, &str)]) -> Result<Self, EnigmaBigramTableError> {
+        let mut ret = Self::new();
+        for &(a, b) in pairs {
+            ret.add_pair(a, b)?;
+        }
+        Ok(ret)
+    }
+
+    // link two bigrams to each other
+    pub fn add_pair(&mut self, a: &str, b: &str) -> Result<(), EnigmaBigramTableError> {
+        let link = (bigram_to_index(a)?, bigram_to_index(b)?);
+
+        // ensure none of them is paired yet,
+        // a bigram can be paired to itself, but only once
+        let free = |idx: usize| self.mapping_array[idx].is_none();
+        if free(link.0) && free(link.1) {
+            self.mapping_array[link.0] = Some(link.1);
+            self.mapping_array[link.1] = Some(link.0);
+            Ok(())
+        } else {
+            Err(EnigmaBigramTableError::AlreadyHavePair(
+                a.to_owned(),
+                b.to_owned(),
+            ))
+        }
+    }
+
+    // whether every one of the 676 bigram have a partner
+    pub fn is_complete(&self) -> bool {
+        self.mapping_array.iter().all(|m| m.is_some())
+    }
+
+    pub fn substitute(&self, bigram: &str) -> Option<String> {
+        let idx = bigram_to_index(bigram).ok()?;
+        self.mapping_array[idx].map(index_to_bigram)
+    }
+}
+
+fn bigram_to_index(bigram: &str) -> Result<usize, EnigmaBigramTableError> {
+    let b = bigram.as_bytes();
+    if b.len() != 2 || !is_enigma_text(bigram) {
+        Err(EnigmaBigramTableError::InvalidBigram)
+    } else {
+        Ok((b[0] - b'A') as usize * 26 + (b[1] - b'A') as usize)
+    }
+}
+
+fn index_to_bigram(idx: usize) -> String {
+    let b = [(idx / 26) as u8 + b'A', (idx % 26) as u8 + b'A'];
+    String::from_utf8_lossy(&b).into_owned()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaNavalMessage {
+    // two four letter groups, sent at the start and again at the end
+    pub indicator: [String; 2],
+    // cipher text without any grouping
+    pub body: String,
+}
+
+impl EnigmaNavalMessage {
+    // run the whole procedure on the sending side.
+    // `fillers` are the two letters that complete the trigram rows.
+    pub fn encode(
+        machine: &mut EnigmaMachine,
+        table: &EnigmaBigramTable,
+        grundstellung: &str,
+        key_trigram: &str,
+        message_trigram: &str,
+        fillers: (char, char),
+        plain_text: &str,
+    ) -> Result<Self, EnigmaMessageError> {
+        check_key(machine, grundstellung)?;
+        check_trigram(machine, message_trigram)?;
+        if key_trigram.len() != 3 || !is_enigma_text(key_trigram) {
+            return Err(EnigmaMessageError::InvalidKey);
+        }
+        if !fillers.0.is_ascii_uppercase() || !fillers.1.is_ascii_uppercase() {
+            return Err(EnigmaMessageError::InvalidKey);
+        }
+        if !is_enigma_text(plain_text) {
+            return Err(EnigmaMessageError::InvalidText);
+        }
+
+        // write the trigrams one above the other and substitute the columns
+        let top = format!("{}{}", fillers.0, key_trigram);
+        let bottom = format!("{}{}", message_trigram, fillers.1);
+        let mut indicator = [String::new(), String::new()];
+        for (t, b) in top.chars().zip(bottom.chars()) {
+            let bigram = format!("{}{}", t, b);
+            let sub = table
+                .substitute(&bigram)
+                .ok_or(EnigmaMessageError::BigramNotInTable(bigram))?;
+            let mut sub = sub.chars();
+            indicator[0].push(sub.next().unwrap());
+            indicator[1].push(sub.next().unwrap());
+        }
+
+        // the message key is the second trigram enciphered at the grundstellung,
+        // set on the windows left to right
+        machine.set_window(grundstellung);
+        let message_key = machine.encode_str(message_trigram);
+
+        machine.set_window(&message_window(grundstellung, &message_key));
+        let body = machine.encode_str(plain_text);
+
+        Ok(Self { indicator, body })
+    }
+
+    // undo the bigram substitution, get back (key trigram, message trigram)
+    pub fn recover_trigrams(
+        &self,
+        table: &EnigmaBigramTable,
+    ) -> Result<(String, String), EnigmaMessageError> {
+        if self
+            .indicator
+            .iter()
+            .any(|g| g.len() != 4 || !is_enigma_text(g))
+        {
+            return Err(EnigmaMessageError::InvalidKey);
+        }
+
+        let mut top = String::new();
+        let mut bottom = String::new();
+        for (a, b) in self.indicator[0].chars().zip(self.indicator[1].chars()) {
+            let bigram = format!("{}{}", a, b);
+            let sub = table
+                .substitute(&bigram)
+                .ok_or(EnigmaMessageError::BigramNotInTable(bigram))?;
+            let mut sub = sub.chars();
+            top.push(sub.next().unwrap());
+            bottom.push(sub.next().unwrap());
+        }
+
+        // drop the fillers
+        Ok((top[1..].to_owned(), bottom[..3].to_owned()))
+    }
+
+    // recover the plain text on the receiving side
+    pub fn decode(
+        &self,
+        machine: &mut EnigmaMachine,
+        table: &EnigmaBigramTable,
+        grundstellung: &str,
+    ) -> Result<String, EnigmaMessageError> {
+        check_key(machine, grundstellung)?;
+        if !is_enigma_text(&self.body) {
+            return Err(EnigmaMessageError::InvalidText);
+        }
+        let (_, message_trigram) = self.recover_trigrams(table)?;
+        check_trigram(machine, &message_trigram)?;
+
+        machine.set_window(grundstellung);
+        let message_key = machine.encode_str(&message_trigram);
+
+        machine.set_window(&message_window(grundstellung, &message_key));
+        Ok(machine.encode_str(&self.body))
+    }
+
+    // read a message in four letter groups,
+    // the indicator groups come first and are repeated at the end
+    pub fn parse(s: &str) -> Result<Self, EnigmaMessageError> {
+        let groups = s.split_whitespace().collect::<Vec<&str>>();
+        if groups.len() < 4 || groups.iter().any(|g| !is_enigma_text(g)) {
+            return Err(EnigmaMessageError::InvalidText);
+        }
+
+        let (head, rest) = groups.split_at(2);
+        let (body, tail) = rest.split_at(rest.len() - 2);
+        if head.iter().any(|g| g.len() != 4) {
+            return Err(EnigmaMessageError::InvalidKey);
+        }
+        if head != tail {
+            return Err(EnigmaMessageError::IndicatorMismatch);
+        }
+
+        Ok(Self {
+            indicator: [head[0].to_owned(), head[1].to_owned()],
+            body: body.concat(),
+        })
+    }
+}
+
+// a trigram is typed on a three rotor machine or an M4
+fn check_trigram(machine: &EnigmaMachine, trigram: &str) -> Result<(), EnigmaMessageError> {
+    let rotor_count = machine.rotor_count();
+    if trigram.len() != 3 || !is_enigma_text(trigram) || !(3..=4).contains(&rotor_count) {
+        Err(EnigmaMessageError::InvalidKey)
+    } else {
+        Ok(())
+    }
+}
+
+// message key on the windows, with the greek wheel of an M4 left at the grundstellung
+fn message_window(grundstellung: &str, message_key: &str) -> String {
+    format!("{}{}", written_to_window(message_key), &grundstellung[3..])
+}
+
+// indicator, body and indicator again, all in four letter groups
+impl fmt::Display for EnigmaNavalMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut groups = self.indicator.to_vec();
+        groups.extend(
+            self.body
+                .as_bytes()
+                .chunks(4)
+                .map(|group| String::from_utf8_lossy(group).into_owned()),
+        );
+        groups.extend(self.indicator.iter().cloned());
+        write!(f, "{}", groups.join(" "))
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use crate::{
+    enigma_batch::*, enigma_machine::*, enigma_procedure::*, enigma_stats::*, enigma_top::*,
+    enigma_types::*,
+};
+
+// run a search on every core.
+// the key space is cut into work items, a run of start positions under one
+// wheel order, and each thread keep taking the next item until none is left.
+// `EnigmaMachine` is full of `Rc` and can't be sent to another thread, so
+// only keys and settings go across and each thread build its own machine,
+// an `EnigmaBatch` for every turn of the fast rotor in an item.
+// threads tell the caller's thread how far they got, the progress callback
+// is only ever called from there.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaScoredKey {
+    pub key: EnigmaKey,
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaWorkItem {
+    // wheel order, ring setting and plugboard to use, the window is ignored
+    pub key: EnigmaKey,
+    // start positions `first..first + count`, see `window_from_index`
+    pub first: usize,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaSearchProgress {
+    // work items finished, out of the total
+    pub done: usize,
+    pub total: usize,
+    pub best: Option<EnigmaScoredKey>,
+}
+
+pub struct EnigmaParallelSearch<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    pub top_n: usize,
+    pub threads: usize,
+    // start positions in one work item
+    pub chunk: usize,
+    // the same for every key tried, empty ring is all 'A'
+    pub ring: String,
+    pub wires: Vec<EnigmaPlugBoardWire>,
+}
+
+impl EnigmaWorkItem {
+    // the key of every start position in the item
+    pub fn keys(&self) -> impl Iterator<Item = EnigmaKey> + '_ {
+        let rotor_count = self.key.rotor_order.len();
+        (self.first..self.first + self.count).map(move |idx| EnigmaKey {
+            window: EnigmaMachine::window_from_index(idx, rotor_count),
+            ..self.key.clone()
+        })
+    }
+}
+
+impl<'a> EnigmaParallelSearch<'a> {
+    // keep the `top_n` best keys, one thread for each core
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>], reflector: &'a str, top_n: usize) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            top_n,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            chunk: 26 * 26,
+            ring: String::new(),
+            wires: vec![],
+        }
+    }
+
+    // every wheel order that can be made from the rotors, cut into items
+    pub fn work_items(&self, rotor_count: usize) -> Vec<EnigmaWorkItem> {
+        let orders = EnigmaRotorSetting::wheel_orders(self.rotors.len(), rotor_count);
+        self.work_items_for(&orders)
+    }
+
+    pub fn work_items_for(&self, orders: &[Vec<usize>]) -> Vec<EnigmaWorkItem> {
+        let chunk = self.chunk.max(1);
+        let mut ret = vec![];
+        for order in orders {
+            let ring = if self.ring.is_empty() {
+                "A".repeat(order.len())
+            } else {
+                self.ring.clone()
+            };
+            let positions = 26_usize.pow(order.len() as u32);
+            for first in (0..positions).step_by(chunk) {
+                ret.push(EnigmaWorkItem {
+                    key: EnigmaKey {
+                        rotor_order: order.clone(),
+                        ring: ring.clone(),
+                        window: "A".repeat(order.len()),
+                        wires: self.wires.clone(),
+                    },
+                    first,
+                    count: chunk.min(positions - first),
+                });
+            }
+        }
+        ret
+    }
+
+    // score the decrypt of every key in the items.
+    // `cancel` is looked at between start positions, when it is set the
+    // best keys found so far are returned.
+    pub fn run(
+        &self,
+        cipher_text: &str,
+        items: &[EnigmaWorkItem],
+        fitness: &(dyn EnigmaFitness + Sync),
+        progress: &mut dyn FnMut(&EnigmaSearchProgress),
+        cancel: &AtomicBool,
+    ) -> Vec<EnigmaScoredKey> {
+        if !is_enigma_text(cipher_text) {
+            return vec![];
+        }
+
+        let work = |item: &EnigmaWorkItem, cancel: &AtomicBool| {
+            let mut ret = vec![];
+            let mut all = item.keys().peekable();
+            // a turn of the fast rotor decrypted in one go, so a cancel is
+            // seen within 26 start positions
+            while all.peek().is_some() {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                let keys = all.by_ref().take(26).collect::<Vec<EnigmaKey>>();
+                let scores = match EnigmaBatch::new(&self.rotors, self.reflector, &keys) {
+                    Ok(batch) => batch.score_all(cipher_text, fitness),
+                    // a rotor wired wrong, the batch doesn't copy the way
+                    // the machine build it, so a machine score those keys
+                    Err(EnigmaKeyError::InvalidRotor(idx)) if idx < self.rotors.len() => keys
+                        .iter()
+                        .map(|key| {
+                            EnigmaMachine::new_with_key(&self.rotors, self.reflector, key)
+                                .map_or(f64::NEG_INFINITY, |mut machine| {
+                                    fitness.score(&machine.encode_str(cipher_text))
+                                })
+                        })
+                        .collect(),
+                    // key point past the rotors or can't be set,
+                    // there is nothing to score
+                    Err(_) => vec![],
+                };
+                for (key, score) in keys.into_iter().zip(scores) {
+                    keep_top(&mut ret, EnigmaScoredKey { key, score }, self.top_n, |c| {
+                        c.score
+                    });
+                }
+            }
+            ret
+        };
+        Self::run_with(self.threads, self.top_n, items, work, progress, cancel)
+    }
+
+    // the part that hand out items and gather results, for searches that
+    // score a work item some other way (bombe stops, plugboard climbing...).
+    // `work` return the best keys of one item.
+    pub fn run_with<I, F>(
+        threads: usize,
+        top_n: usize,
+        items: &[I],
+        work: F,
+        progress: &mut dyn FnMut(&EnigmaSearchProgress),
+        cancel: &AtomicBool,
+    ) -> Vec<EnigmaScoredKey>
+    where
+        I: Sync,
+        F: Fn(&I, &AtomicBool) -> Vec<EnigmaScoredKey> + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel::<Vec<EnigmaScoredKey>>();
+        let mut ret = vec![];
+
+        thread::scope(|scope| {
+            for _ in 0..threads.clamp(1, items.len().max(1)) {
+                let sender = sender.clone();
+                let (next, work) = (&next, &work);
+                scope.spawn(move || loop {
+                    if cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    match items.get(idx) {
+                        Some(item) => {
+                            if sender.send(work(item, cancel)).is_err() {
+                                break;
+                            }
+                        }
+                        None => break,
+                    }
+                });
+            }
+            // the receiver end once every thread has dropped its sender
+            drop(sender);
+
+            for (idx, found) in receiver.into_iter().enumerate() {
+                found
+                    .into_iter()
+                    .for_each(|candidate| keep_top(&mut ret, candidate, top_n, |c| c.score));
+                progress(&EnigmaSearchProgress {
+                    done: idx + 1,
+                    total: items.len(),
+                    best: ret.first().cloned(),
+                });
+            }
+        });
+        ret
+    }
+}
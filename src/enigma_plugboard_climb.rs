@@ -0,0 +1,225 @@
+use crate::{
+    enigma_ioc_search::*, enigma_machine::*, enigma_procedure::*, enigma_random::*,
+    enigma_stats::*, enigma_types::*,
+};
+
+// recover the plugboard once wheel order and start position are known.
+// rotor positions don't depend on the plugboard, so the scrambler at every
+// position is worked out once, and a plugboard P give
+//
+//     plain = P(E_i(P(cipher)))
+//
+// starting from some board, try to add, remove or swap wires between every
+// pair of letters and keep the change when the fitness go up, until nothing
+// helps any more. then start again from a random board.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaPlugBoardClimbResult {
+    pub wires: Vec<EnigmaPlugBoardWire>,
+    // score of the last fitness stage
+    pub score: f64,
+}
+
+pub struct EnigmaPlugBoardClimb<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    window: String,
+    // most wires the board can have, 10 was the usual
+    pub max_wires: usize,
+    // climbs from a random board after the first one from an empty board
+    pub restarts: usize,
+}
+
+impl<'a> EnigmaPlugBoardClimb<'a> {
+    // rotors in the order they are put in the machine
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>], reflector: &'a str, window: &str) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            window: window.to_owned(),
+            max_wires: 10,
+            restarts: 5,
+        }
+    }
+
+    // continue from what the index of coincidence search found
+    pub fn new_with_candidate(
+        rotors: &[EnigmaRotorSetting<'a>],
+        reflector: &'a str,
+        candidate: &EnigmaIocCandidate,
+    ) -> Self {
+        let ordered = candidate
+            .rotor_order
+            .iter()
+            .map(|&idx| rotors[idx])
+            .collect::<Vec<EnigmaRotorSetting>>();
+        Self::new(&ordered, reflector, &candidate.window)
+    }
+
+    // climb with each fitness in turn, e.g. index of coincidence first
+    // to get the board roughly right, then n-grams to finish it.
+    pub fn run(
+        &self,
+        cipher_text: &str,
+        stages: &[&dyn EnigmaFitness],
+        random: &mut EnigmaRandom,
+    ) -> Option<EnigmaPlugBoardClimbResult> {
+        if !is_enigma_text(cipher_text) || stages.is_empty() {
+            return None;
+        }
+
+        let scramblers = self.scramblers(cipher_text.len());
+        let cipher = cipher_text
+            .bytes()
+            .map(|b| (b - b'A') as usize)
+            .collect::<Vec<usize>>();
+
+        let mut best: Option<([usize; 26], f64)> = None;
+        for restart in 0..=self.restarts {
+            let mut board = if restart == 0 {
+                Self::empty_board()
+            } else {
+                self.random_board(random)
+            };
+
+            let mut score = 0.0;
+            for fitness in stages {
+                score = self.climb(&scramblers, &cipher, &mut board, *fitness);
+            }
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((board, score));
+            }
+        }
+
+        best.map(|(board, score)| EnigmaPlugBoardClimbResult {
+            wires: Self::wires_of(&board),
+            score,
+        })
+    }
+
+    // scrambler of every position in the message
+    fn scramblers(&self, len: usize) -> Vec<[usize; 26]> {
+        let mut machine = EnigmaMachine::new_with_all_setting(self.rotors.clone(), self.reflector);
+        machine.set_window(&self.window);
+        (0..len)
+            .map(|_| {
+                machine.step();
+                machine.scramble_table()
+            })
+            .collect()
+    }
+
+    fn decode(scramblers: &[[usize; 26]], cipher: &[usize], board: &[usize; 26]) -> String {
+        cipher
+            .iter()
+            .zip(scramblers.iter())
+            .map(|(&c, scrambler)| (board[scrambler[board[c]]] as u8 + b'A') as char)
+            .collect()
+    }
+
+    fn climb(
+        &self,
+        scramblers: &[[usize; 26]],
+        cipher: &[usize],
+        board: &mut [usize; 26],
+        fitness: &dyn EnigmaFitness,
+    ) -> f64 {
+        let mut best = fitness.score(&Self::decode(scramblers, cipher, board));
+        loop {
+            let mut improved = false;
+            for i in 0..26 {
+                for j in i + 1..26 {
+                    for candidate in self.moves(board, i, j) {
+                        let score = fitness.score(&Self::decode(scramblers, cipher, &candidate));
+                        if score > best {
+                            *board = candidate;
+                            best = score;
+                            improved = true;
+                        }
+                    }
+                }
+            }
+            if !improved {
+                return best;
+            }
+        }
+    }
+
+    // boards next to this one by changing the wires of i and j
+    fn moves(&self, board: &[usize; 26], i: usize, j: usize) -> Vec<[usize; 26]> {
+        let (pi, pj) = (board[i], board[j]);
+
+        // i and j are wired together, take the wire out
+        if pi == j {
+            let mut next = *board;
+            next[i] = i;
+            next[j] = j;
+            return vec![next];
+        }
+
+        // pull out what i and j are plugged to, then wire i to j
+        let mut base = *board;
+        [i, j].iter().for_each(|&x| {
+            let p = base[x];
+            base[x] = x;
+            base[p] = p;
+        });
+        let mut ret = vec![];
+
+        let mut next = base;
+        next[i] = j;
+        next[j] = i;
+        if Self::wire_count(&next) <= self.max_wires {
+            ret.push(next);
+        }
+
+        // both were plugged, swap their partners
+        if pi != i && pj != j {
+            let mut next = next;
+            next[pi] = pj;
+            next[pj] = pi;
+            if Self::wire_count(&next) <= self.max_wires {
+                ret.push(next);
+            }
+        }
+        ret
+    }
+
+    fn empty_board() -> [usize; 26] {
+        let mut ret = [0; 26];
+        ret.iter_mut().enumerate().for_each(|(idx, p)| *p = idx);
+        ret
+    }
+
+    fn random_board(&self, random: &mut EnigmaRandom) -> [usize; 26] {
+        let mut ret = Self::empty_board();
+        let wires = random.next_index(self.max_wires.min(13) + 1);
+        while Self::wire_count(&ret) < wires {
+            let (a, b) = (random.next_index(26), random.next_index(26));
+            if a != b && ret[a] == a && ret[b] == b {
+                ret[a] = b;
+                ret[b] = a;
+            }
+        }
+        ret
+    }
+
+    fn wire_count(board: &[usize; 26]) -> usize {
+        board
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &p)| idx < p)
+            .count()
+    }
+
+    fn wires_of(board: &[usize; 26]) -> Vec<EnigmaPlugBoardWire> {
+        board
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &p)| idx < p)
+            .map(|(idx, &p)| {
+                EnigmaPlugBoardWire((idx as u8 + b'A') as char, (p as u8 + b'A') as char)
+            })
+            .collect()
+    }
+}
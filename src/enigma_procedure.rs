@@ -0,0 +1,325 @@
+use std::fmt;
+
+use crate::{enigma_format::*, enigma_machine::*, enigma_random::*, enigma_types::*};
+
+// Heer and Luftwaffe procedure after May 1940.
+// operator choose a start position (grundstellung) and send it in clear,
+// then the message key is enciphered once at that position,
+// and the body is enciphered with the message key as start position.
+// like everywhere else in the crate, grundstellung and message key are
+// windows, fast rotor first. only where the operator write or type a key
+// it is left to right, see `written_to_window`.
+
+// longer text is split into parts, each has its own header and keys,
+// `2TLE = 1TL` in the header means part 1 of 2.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnigmaMessagePart {
+    pub number: usize,
+    pub total: usize,
+}
+
+// clear-text header in front of a message, e.g. `1220 = 204 = QSZ VUB =`
+// or `1840 = 2TLE = 1TL = 179 = WXC KCH =` for a multi-part message
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaMessageHeader {
+    // time of origin, four digits
+    pub time: String,
+    // None when the message is sent in one piece
+    pub part: Option<EnigmaMessagePart>,
+    // letters in the message, kenngruppe included
+    pub letter_count: usize,
+    // start position chosen by the operator, sent in clear.
+    // fast rotor first, the header text has it the other way round
+    pub grundstellung: String,
+    // message key enciphered once at the grundstellung, as sent
+    pub encoded_message_key: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaMessage {
+    pub header: EnigmaMessageHeader,
+    // first group of the text, two filler letters and the three letter kenngruppe,
+    // it is not enciphered
+    pub kenngruppe: String,
+    // cipher text without any grouping
+    pub body: String,
+}
+
+impl EnigmaMessage {
+    // run the whole procedure on the sending side
+    pub fn encode(
+        machine: &mut EnigmaMachine,
+        time: &str,
+        grundstellung: &str,
+        message_key: &str,
+        kenngruppe: &str,
+        plain_text: &str,
+    ) -> Result<Self, EnigmaMessageError> {
+        check_key(machine, grundstellung)?;
+        check_key(machine, message_key)?;
+        if !is_time(time) {
+            return Err(EnigmaMessageError::InvalidHeader);
+        }
+        if kenngruppe.len() != 5 || !is_enigma_text(kenngruppe) {
+            return Err(EnigmaMessageError::InvalidKenngruppe);
+        }
+        if !is_enigma_text(plain_text) {
+            return Err(EnigmaMessageError::InvalidText);
+        }
+
+        // encipher message key once at the grundstellung,
+        // typed left to right as it is read from the windows
+        machine.set_window(grundstellung);
+        let encoded_message_key = machine.encode_str(&window_to_written(message_key));
+
+        // then the text itself, start from the message key
+        machine.set_window(message_key);
+        let body = machine.encode_str(plain_text);
+
+        Ok(Self {
+            header: EnigmaMessageHeader {
+                time: time.to_owned(),
+                part: None,
+                letter_count: kenngruppe.len() + body.len(),
+                grundstellung: grundstellung.to_owned(),
+                encoded_message_key,
+            },
+            kenngruppe: kenngruppe.to_owned(),
+            body,
+        })
+    }
+
+    // split a long text into parts of at most `max_letters` (kenngruppe included),
+    // every part get a fresh grundstellung and message key
+    pub fn encode_parts(
+        machine: &mut EnigmaMachine,
+        time: &str,
+        kenngruppe: &str,
+        plain_text: &str,
+        max_letters: usize,
+        random: &mut EnigmaRandom,
+    ) -> Result<Vec<Self>, EnigmaMessageError> {
+        if max_letters <= kenngruppe.len() {
+            return Err(EnigmaMessageError::InvalidPartLength);
+        }
+        if plain_text.is_empty() {
+            return Err(EnigmaMessageError::InvalidText);
+        }
+
+        let chunks = plain_text
+            .as_bytes()
+            .chunks(max_letters - kenngruppe.len())
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect::<Vec<String>>();
+        let total = chunks.len();
+
+        let mut ret = vec![];
+        for (idx, chunk) in chunks.iter().enumerate() {
+            let grundstellung = random.next_letters(machine.rotor_count());
+            let message_key = random.next_letters(machine.rotor_count());
+            let mut message = Self::encode(
+                machine,
+                time,
+                &grundstellung,
+                &message_key,
+                kenngruppe,
+                chunk,
+            )?;
+            if total > 1 {
+                message.header.part = Some(EnigmaMessagePart {
+                    number: idx + 1,
+                    total,
+                });
+            }
+            ret.push(message);
+        }
+        Ok(ret)
+    }
+
+    // put parts back together, they can come in any order
+    pub fn decode_parts(
+        parts: &[Self],
+        machine: &mut EnigmaMachine,
+    ) -> Result<String, EnigmaMessageError> {
+        let first = parts.first().ok_or(EnigmaMessageError::MissingPart(1))?;
+        let total = match first.header.part {
+            Some(part) => part.total,
+            // sent in one piece
+            None => return first.decode(machine),
+        };
+
+        let mut ret = String::new();
+        for number in 1..=total {
+            let message = parts
+                .iter()
+                .find(|p| p.header.part == Some(EnigmaMessagePart { number, total }))
+                .ok_or(EnigmaMessageError::MissingPart(number))?;
+            ret.push_str(&message.decode(machine)?);
+        }
+        Ok(ret)
+    }
+
+    // recover the message key on the receiving side
+    pub fn decode_message_key(
+        &self,
+        machine: &mut EnigmaMachine,
+    ) -> Result<String, EnigmaMessageError> {
+        check_key(machine, &self.header.grundstellung)?;
+        check_key(machine, &self.header.encoded_message_key)?;
+
+        machine.set_window(&self.header.grundstellung);
+        let message_key = machine.encode_str(&self.header.encoded_message_key);
+        Ok(written_to_window(&message_key))
+    }
+
+    // recover the plain text
+    pub fn decode(&self, machine: &mut EnigmaMachine) -> Result<String, EnigmaMessageError> {
+        if !is_enigma_text(&self.body) {
+            return Err(EnigmaMessageError::InvalidText);
+        }
+        let message_key = self.decode_message_key(machine)?;
+
+        machine.set_window(&message_key);
+        Ok(machine.encode_str(&self.body))
+    }
+
+    // read a message in the form it was sent,
+    // header in the first line, then the groups
+    pub fn parse(s: &str) -> Result<Self, EnigmaMessageError> {
+        let mut lines = s.trim().lines();
+        let header = EnigmaMessageHeader::parse(lines.next().unwrap_or(""))?;
+
+        // grouping and line break doesn't matter
+        let text = EnigmaGroupFormat::parse(&lines.collect::<Vec<&str>>().join("\n"))?;
+        if text.len() < 5 {
+            return Err(EnigmaMessageError::InvalidKenngruppe);
+        }
+        if text.len() != header.letter_count {
+            return Err(EnigmaMessageError::LetterCountMismatch {
+                expected: header.letter_count,
+                found: text.len(),
+            });
+        }
+
+        let (kenngruppe, body) = text.split_at(5);
+        Ok(Self {
+            header,
+            kenngruppe: kenngruppe.to_owned(),
+            body: body.to_owned(),
+        })
+    }
+}
+
+impl EnigmaMessageHeader {
+    // `1220 = 204 = QSZ VUB =`
+    pub fn parse(s: &str) -> Result<Self, EnigmaMessageError> {
+        let mut fields = s
+            .split('=')
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<&str>>();
+
+        // take out `2TLE = 1TL` if there is one
+        let part = if fields.len() == 5 {
+            let total = fields[1]
+                .strip_suffix("TLE")
+                .and_then(|n| n.parse::<usize>().ok());
+            let number = fields[2]
+                .strip_suffix("TL")
+                .and_then(|n| n.parse::<usize>().ok());
+            match (number, total) {
+                (Some(number), Some(total)) if number >= 1 && number <= total => {
+                    fields.drain(1..3);
+                    Some(EnigmaMessagePart { number, total })
+                }
+                _ => return Err(EnigmaMessageError::InvalidHeader),
+            }
+        } else {
+            None
+        };
+
+        if fields.len() != 3 || !is_time(fields[0]) {
+            return Err(EnigmaMessageError::InvalidHeader);
+        }
+
+        let letter_count = fields[1]
+            .parse::<usize>()
+            .map_err(|_| EnigmaMessageError::InvalidHeader)?;
+
+        let keys = fields[2].split_whitespace().collect::<Vec<&str>>();
+        if keys.len() != 2
+            || keys[0].len() != keys[1].len()
+            || !keys
+                .iter()
+                .all(|key| !key.is_empty() && is_enigma_text(key))
+        {
+            return Err(EnigmaMessageError::InvalidKey);
+        }
+
+        Ok(Self {
+            time: fields[0].to_owned(),
+            part,
+            letter_count,
+            grundstellung: written_to_window(keys[0]),
+            encoded_message_key: keys[1].to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for EnigmaMessageHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = ", self.time)?;
+        if let Some(part) = self.part {
+            write!(f, "{}TLE = {}TL = ", part.total, part.number)?;
+        }
+        write!(
+            f,
+            "{} = {} {} =",
+            self.letter_count,
+            window_to_written(&self.grundstellung),
+            self.encoded_message_key
+        )
+    }
+}
+
+impl EnigmaMessage {
+    // header in the first line, then kenngruppe and body in groups
+    pub fn format(&self, format: &EnigmaGroupFormat) -> String {
+        let text = format!("{}{}", self.kenngruppe, self.body);
+        format!("{}\n{}", self.header, format.format(&text))
+    }
+}
+
+impl fmt::Display for EnigmaMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&EnigmaGroupFormat::HEER))
+    }
+}
+
+// only 'A' to 'Z' can be typed on the machine
+pub(crate) fn is_enigma_text(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+fn is_time(s: &str) -> bool {
+    s.len() == 4 && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+// operators wrote and typed a key the way they read the windows, left to
+// right, so the fast rotor come last. the machine take the fast rotor first.
+pub(crate) fn written_to_window(written: &str) -> String {
+    written.chars().rev().collect()
+}
+
+pub(crate) fn window_to_written(window: &str) -> String {
+    window.chars().rev().collect()
+}
+
+// a key should give every rotor a window
+pub(crate) fn check_key(machine: &EnigmaMachine, key: &str) -> Result<(), EnigmaMessageError> {
+    if key.len() != machine.rotor_count() || !is_enigma_text(key) {
+        Err(EnigmaMessageError::InvalidKey)
+    } else {
+        Ok(())
+    }
+}
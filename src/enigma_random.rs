@@ -0,0 +1,72 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// small pseudo random generator (xorshift64*),
+// good enough to pick keys and restart points, NOT for real secrets.
+// same seed always give the same sequence, so searches can be repeated.
+#[derive(Clone, Debug)]
+pub struct EnigmaRandom {
+    state: u64,
+}
+
+impl Default for EnigmaRandom {
+    // seed from the clock
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+}
+
+impl EnigmaRandom {
+    pub fn new(seed: u64) -> Self {
+        // spread the seed (splitmix64), state must never be zero
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Self {
+            state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z },
+        }
+    }
+
+    // current state, can be given back to `new_with_state` to continue
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn new_with_state(state: u64) -> Self {
+        Self {
+            state: if state == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                state
+            },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // number in 0..n
+    pub fn next_index(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // number in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    // random letters from 'A' to 'Z', e.g. for a message key
+    pub fn next_letters(&mut self, len: usize) -> String {
+        (0..len)
+            .map(|_| (self.next_index(26) as u8 + b'A') as char)
+            .collect::<String>()
+    }
+}
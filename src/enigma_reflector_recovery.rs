@@ -0,0 +1,150 @@
+use crate::{enigma_component::*, enigma_machine::*, enigma_procedure::*, enigma_types::*};
+
+// recover the wiring of an unknown reflector from known plain text,
+// with rotors, plugboard and start position known.
+// the plain letter go forward through the rotors to the reflector, and
+// the cipher letter, sent forward the same way, end up on the contact the
+// reflector sent the signal back from. so each letter of text give one
+// pair of the reflector, and the pairs must all agree.
+// only the way in to the reflector is needed, so the machine is built with
+// a straight through reflector that is never used.
+
+pub struct EnigmaReflectorRecovery<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    wires: Vec<EnigmaPlugBoardWire>,
+    entry_wheel: &'a str,
+    ring: String,
+    window: String,
+}
+
+impl<'a> EnigmaReflectorRecovery<'a> {
+    // rotors in the order they are put in the machine,
+    // ring and window fast rotor first like the machine take them
+    pub fn new(
+        rotors: &[EnigmaRotorSetting<'a>],
+        wires: &[EnigmaPlugBoardWire],
+        ring: &str,
+        window: &str,
+    ) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            wires: wires.to_vec(),
+            entry_wheel: EnigmaEntryWheel::ABC,
+            ring: ring.to_owned(),
+            window: window.to_owned(),
+        }
+    }
+
+    // keys in the order of the contacts they are wired to, straight through if not set
+    pub fn set_entry_wheel(&mut self, s: &'a str) {
+        self.entry_wheel = s;
+    }
+
+    pub fn run(
+        &self,
+        plain_text: &str,
+        cipher_text: &str,
+    ) -> Result<EnigmaReflector, EnigmaRecoveryError> {
+        if !is_enigma_text(plain_text) || !is_enigma_text(cipher_text) {
+            return Err(EnigmaRecoveryError::InvalidText);
+        }
+        if plain_text.len() != cipher_text.len() {
+            return Err(EnigmaRecoveryError::LengthMismatch);
+        }
+
+        // both letters of a position go in with the rotors at the same place
+        let mut machine = self.machine()?;
+        let plug_board = self.plug_board()?;
+
+        let mut pairs = [26; 26];
+        let mut found_at = [0; 26];
+        let mut conflicts = vec![];
+        let to_char = |idx: usize| (idx as u8 + b'A') as char;
+        for (position, (p, c)) in plain_text.bytes().zip(cipher_text.bytes()).enumerate() {
+            machine.step();
+            let a = machine.reflector_side_index(plug_board.encode_index((p - b'A') as usize));
+            let b = machine.reflector_side_index(plug_board.encode_index((c - b'A') as usize));
+
+            // a letter can't be sent back on itself,
+            // report it as clashing with itself
+            if a == b {
+                conflicts.push(EnigmaReflectorConflict {
+                    position,
+                    pair: (to_char(a), to_char(b)),
+                    earlier: position,
+                    earlier_pair: (to_char(a), to_char(b)),
+                });
+                continue;
+            }
+
+            // or with what is already known
+            let clash = [(a, b), (b, a)]
+                .into_iter()
+                .find(|&(x, y)| pairs[x] != 26 && pairs[x] != y);
+            if let Some((x, _)) = clash {
+                conflicts.push(EnigmaReflectorConflict {
+                    position,
+                    pair: (to_char(a), to_char(b)),
+                    earlier: found_at[x],
+                    earlier_pair: (to_char(x), to_char(pairs[x])),
+                });
+                continue;
+            }
+
+            if pairs[a] == 26 {
+                pairs[a] = b;
+                pairs[b] = a;
+                found_at[a] = position;
+                found_at[b] = position;
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(EnigmaRecoveryError::Conflict(conflicts));
+        }
+
+        // the last two letters can only go with each other
+        let free = (0..26).filter(|&x| pairs[x] == 26).collect::<Vec<usize>>();
+        match free.as_slice() {
+            [] => {}
+            &[x, y] => {
+                pairs[x] = y;
+                pairs[y] = x;
+            }
+            _ => {
+                return Err(EnigmaRecoveryError::Underdetermined(
+                    free.into_iter().map(to_char).collect(),
+                ))
+            }
+        }
+
+        Ok(EnigmaReflector::new_with_vec(
+            pairs.iter().map(|&x| x as u8 + b'A').collect(),
+        ))
+    }
+
+    // machine at the start position, the reflector is what we are after
+    fn machine(&self) -> Result<EnigmaMachine, EnigmaRecoveryError> {
+        if self.window.len() != self.rotors.len() || !is_enigma_text(&self.window) {
+            return Err(EnigmaRecoveryError::InvalidWindow);
+        }
+        if self.ring.len() != self.rotors.len() || !is_enigma_text(&self.ring) {
+            return Err(EnigmaRecoveryError::InvalidRing);
+        }
+        let mut ret =
+            EnigmaMachine::new_with_all_setting(self.rotors.clone(), EnigmaEntryWheel::ABC);
+        ret.set_entry_wheel(self.entry_wheel);
+        ret.set_ring(&self.ring);
+        ret.set_window(&self.window);
+        Ok(ret)
+    }
+
+    fn plug_board(&self) -> Result<EnigmaPlugBoard, EnigmaRecoveryError> {
+        let mut ret = EnigmaPlugBoard::new();
+        for &wire in self.wires.iter() {
+            if ret.add_wire(wire).is_err() {
+                return Err(EnigmaRecoveryError::InvalidWire(wire));
+            }
+        }
+        Ok(ret)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    enigma_ioc_search::*, enigma_machine::*, enigma_plugboard_climb::*, enigma_procedure::*,
+    enigma_stats::*, enigma_top::*, enigma_types::*,
+};
+
+// the searches before this one take every ring setting as 'A'.
+// turning a ring and the window by the same amount give the same wiring,
+// only the turnover move. so with the wrong ring on the fast rotor the
+// decrypt is readable until the middle rotor turn over at the wrong place,
+// and garbled after that.
+// try every ring of the fast and middle rotor, move the window with it
+// so the wiring stay where it was, and keep the decrypts that read best.
+// the ring of the other rotors never change a turnover inside a message,
+// so they can be left at 'A'.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaRingCandidate {
+    pub ring: String,
+    pub window: String,
+    pub score: f64,
+}
+
+pub struct EnigmaRingSearch<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    window: String,
+    wires: Vec<EnigmaPlugBoardWire>,
+    top_n: usize,
+}
+
+impl<'a> EnigmaRingSearch<'a> {
+    // rotors in the order they are put in the machine,
+    // window found with every ring at 'A'
+    pub fn new(
+        rotors: &[EnigmaRotorSetting<'a>],
+        reflector: &'a str,
+        window: &str,
+        wires: &[EnigmaPlugBoardWire],
+        top_n: usize,
+    ) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            window: window.to_owned(),
+            wires: wires.to_vec(),
+            top_n,
+        }
+    }
+
+    // finish what the index of coincidence search and the plugboard climb found
+    pub fn new_with_candidate(
+        rotors: &[EnigmaRotorSetting<'a>],
+        reflector: &'a str,
+        candidate: &EnigmaIocCandidate,
+        plug_board: &EnigmaPlugBoardClimbResult,
+        top_n: usize,
+    ) -> Self {
+        let ordered = candidate
+            .rotor_order
+            .iter()
+            .map(|&idx| rotors[idx])
+            .collect::<Vec<EnigmaRotorSetting>>();
+        Self::new(
+            &ordered,
+            reflector,
+            &candidate.window,
+            &plug_board.wires,
+            top_n,
+        )
+    }
+
+    pub fn run(
+        &self,
+        cipher_text: &str,
+        fitness: &dyn EnigmaFitness,
+    ) -> Result<Vec<EnigmaRingCandidate>, EnigmaRingSearchError> {
+        let mut ret = vec![];
+        if !is_enigma_text(cipher_text) {
+            return Err(EnigmaRingSearchError::InvalidText);
+        }
+        if self.window.len() != self.rotors.len() || !is_enigma_text(&self.window) {
+            return Err(EnigmaRingSearchError::InvalidWindow);
+        }
+
+        let mut machine = EnigmaMachine::new_with_all_setting(self.rotors.clone(), self.reflector);
+        for &wire in self.wires.iter() {
+            if machine.add_plug_wire(wire).is_err() {
+                return Err(EnigmaRingSearchError::InvalidWire(wire));
+            }
+        }
+
+        // fast and middle rotor
+        let searched = self.rotors.len().min(2);
+        for idx in 0..26_usize.pow(searched as u32) {
+            let mut ring = EnigmaMachine::window_from_index(idx, searched);
+            ring.extend(std::iter::repeat_n('A', self.rotors.len() - searched));
+            let window = Self::compensate(&self.window, &ring);
+
+            machine.set_ring(&ring);
+            machine.set_window(&window);
+            let score = fitness.score(&machine.encode_str(cipher_text));
+
+            keep_top(
+                &mut ret,
+                EnigmaRingCandidate {
+                    ring,
+                    window,
+                    score,
+                },
+                self.top_n,
+                |c| c.score,
+            );
+        }
+        Ok(ret)
+    }
+
+    // move each window as far as its ring, both are 'A' to 'Z' here
+    fn compensate(window: &str, ring: &str) -> String {
+        window
+            .bytes()
+            .zip(ring.bytes())
+            .map(|(w, r)| ((w - b'A' + r - b'A') % 26 + b'A') as char)
+            .collect()
+    }
+}
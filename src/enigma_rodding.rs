@@ -0,0 +1,261 @@
+use std::fmt;
+
+use crate::{enigma_component::*, enigma_menu::*, enigma_procedure::*, enigma_types::*};
+
+// Knox's rodding, for machines without plugboard.
+// while the middle rotor stand still, middle rotor, left rotor and
+// reflector together act like one fixed reflector R, and the machine
+// at each position is F⁻¹ R F with F the fast rotor (after the entry wheel).
+// so for a crib letter p over cipher letter c, R must pair F(p) with F(c).
+// a rod square list F for every position of the fast rotor. for each
+// fast rotor and start position, read the pairs off the rods and keep
+// the ones where no letter is paired with two different letters.
+// Knox also worked from isomorphs, when no crib was known. two messages
+// with the same plain text and the fast rotor at the same place, but the
+// other rotors somewhere else, have R1 and R2 in place of R. read off the
+// rods, each letter of the first text then go to a letter of the second
+// by the same substitution R2 R1 all along, so the right fast rotor and
+// start position turn both texts into the same pattern of letters.
+
+// F of one rotor at every position, row by position and column by key
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaRodSquare {
+    rods: Vec<[usize; 26]>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaRoddingStop {
+    // index into the rotors given to the rodding
+    pub rotor: usize,
+    // window of the fast rotor before the first letter of the message
+    pub window: char,
+    // pairs of R found, one list for every position the middle rotor
+    // take while the crib is typed
+    pub pairs: Vec<Vec<(char, char)>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaIsomorphStop {
+    // index into the rotors given to the rodding
+    pub rotor: usize,
+    // window of the fast rotor before the first letter of both texts
+    pub window: char,
+    // letters of the first text's rods and where they go on the second's,
+    // one list for every position the middle rotor take
+    pub links: Vec<Vec<(char, char)>>,
+}
+
+pub struct EnigmaRodding<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    entry_wheel: String,
+}
+
+impl EnigmaRodSquare {
+    // keys are in the order of `EnigmaEntryWheel`
+    pub fn new(setting: EnigmaRotorSetting, entry_wheel: &str) -> Self {
+        let entry_wheel = EnigmaEntryWheel::new_with_str(entry_wheel);
+        let mut rotor = EnigmaRotor::new_with_setting(setting, None, None);
+        let rods = (0..26_u8)
+            .map(|position| {
+                rotor.set_window((position + b'A') as char);
+                let mut row = [0; 26];
+                row.iter_mut().enumerate().for_each(|(key, out)| {
+                    *out = rotor.map_forward_index(entry_wheel.encode_forward_index(key));
+                });
+                row
+            })
+            .collect();
+        Self { rods }
+    }
+
+    // letter read off the rod of `key` with the rotor at `window`
+    pub fn rod(&self, window: char, key: char) -> char {
+        let row = &self.rods[(window as u8 - b'A') as usize];
+        (row[(key as u8 - b'A') as usize] as u8 + b'A') as char
+    }
+}
+
+// a row for each position, like the printed squares
+impl fmt::Display for EnigmaRodSquare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (position, row) in self.rods.iter().enumerate() {
+            let line = row
+                .iter()
+                .map(|&idx| (idx as u8 + b'A') as char)
+                .collect::<String>();
+            writeln!(f, "{} {}", (position as u8 + b'A') as char, line)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> EnigmaRodding<'a> {
+    // the rotors that can be in the fast position, and the entry wheel
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>], entry_wheel: &str) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            entry_wheel: entry_wheel.to_owned(),
+        }
+    }
+
+    pub fn rod_square(&self, rotor: usize) -> EnigmaRodSquare {
+        EnigmaRodSquare::new(self.rotors[rotor], &self.entry_wheel)
+    }
+
+    // every fast rotor and start position the crib at `offset` agree with
+    pub fn run(
+        &self,
+        cipher_text: &str,
+        crib: &str,
+        offset: usize,
+    ) -> Result<Vec<EnigmaRoddingStop>, EnigmaRoddingError> {
+        let menu = EnigmaMenu::new(cipher_text, crib, offset).map_err(|e| match e {
+            EnigmaBombeError::InvalidText => EnigmaRoddingError::InvalidText,
+            EnigmaBombeError::CribOutOfRange => EnigmaRoddingError::CribOutOfRange,
+            EnigmaBombeError::CribClash(position) => EnigmaRoddingError::CribClash(position),
+        })?;
+        let mut ret = vec![];
+
+        for (rotor, setting) in self.rotors.iter().enumerate() {
+            let square = self.rod_square(rotor);
+            let notch = (setting.notch as u8 - b'A') as usize;
+
+            for window in 0..26 {
+                if let Some(pairs) = Self::rod(&square, notch, window, menu.links()) {
+                    ret.push(EnigmaRoddingStop {
+                        rotor,
+                        window: (window as u8 + b'A') as char,
+                        pairs,
+                    });
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    // every fast rotor and start position that turn two cipher texts of the
+    // same plain text into isomorphs
+    pub fn isomorphs(
+        &self,
+        first: &str,
+        second: &str,
+    ) -> Result<Vec<EnigmaIsomorphStop>, EnigmaRoddingError> {
+        if !is_enigma_text(first) || !is_enigma_text(second) {
+            return Err(EnigmaRoddingError::InvalidText);
+        }
+        if first.len() != second.len() {
+            return Err(EnigmaRoddingError::LengthMismatch);
+        }
+        let letters = first
+            .bytes()
+            .zip(second.bytes())
+            .map(|(a, b)| ((a - b'A') as usize, (b - b'A') as usize))
+            .collect::<Vec<(usize, usize)>>();
+        let mut ret = vec![];
+
+        for (rotor, setting) in self.rotors.iter().enumerate() {
+            let square = self.rod_square(rotor);
+            let notch = (setting.notch as u8 - b'A') as usize;
+
+            for window in 0..26 {
+                if let Some(links) = Self::rod_isomorph(&square, notch, window, &letters) {
+                    ret.push(EnigmaIsomorphStop {
+                        rotor,
+                        window: (window as u8 + b'A') as char,
+                        links,
+                    });
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    fn rod_isomorph(
+        square: &EnigmaRodSquare,
+        notch: usize,
+        window: usize,
+        letters: &[(usize, usize)],
+    ) -> Option<Vec<Vec<(char, char)>>> {
+        // R2 R1 and the way back for each position of the middle rotor
+        let mut segments: Vec<([usize; 26], [usize; 26])> = vec![([26; 26], [26; 26])];
+
+        for (position, &(x, y)) in letters.iter().enumerate() {
+            // the middle rotor turn over in both messages at the same place
+            if position > 0 && (window + position) % 26 == notch {
+                segments.push(([26; 26], [26; 26]));
+            }
+
+            let row = &square.rods[(window + position + 1) % 26];
+            let (a, b) = (row[x], row[y]);
+            let (there, back) = segments.last_mut().unwrap();
+            if (there[a] != 26 && there[a] != b) || (back[b] != 26 && back[b] != a) {
+                return None;
+            }
+            there[a] = b;
+            back[b] = a;
+        }
+
+        Some(
+            segments
+                .iter()
+                .map(|(there, _)| {
+                    there
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &b)| b != 26)
+                        .map(|(a, &b)| ((a as u8 + b'A') as char, (b as u8 + b'A') as char))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    fn rod(
+        square: &EnigmaRodSquare,
+        notch: usize,
+        window: usize,
+        links: &[EnigmaMenuLink],
+    ) -> Option<Vec<Vec<(char, char)>>> {
+        // pairing of R for each position of the middle rotor, 26 is not known yet
+        let mut segments: Vec<[usize; 26]> = vec![];
+
+        // fast rotor step before each letter,
+        // and turn the middle one over when it leave the notch
+        let turnovers = |position: usize| {
+            (0..=position)
+                .filter(|t| (window + t) % 26 == notch)
+                .count()
+        };
+        let before = links.first().map_or(0, |link| turnovers(link.position));
+
+        for link in links {
+            let segment = turnovers(link.position) - before;
+            while segments.len() <= segment {
+                segments.push([26; 26]);
+            }
+
+            let row = &square.rods[(window + link.position + 1) % 26];
+            let (a, b) = (row[link.crib], row[link.cipher]);
+            let pairing = &mut segments[segment];
+            if (pairing[a] != 26 && pairing[a] != b) || (pairing[b] != 26 && pairing[b] != a) {
+                return None;
+            }
+            pairing[a] = b;
+            pairing[b] = a;
+        }
+
+        Some(
+            segments
+                .iter()
+                .map(|pairing| {
+                    pairing
+                        .iter()
+                        .enumerate()
+                        .filter(|&(a, &b)| a < b && b != 26)
+                        .map(|(a, &b)| ((a as u8 + b'A') as char, (b as u8 + b'A') as char))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{enigma_machine::*, enigma_procedure::*, enigma_stats::*, enigma_top::*};
+
+// wheel order, rings and plugboard are known but the indicator is lost.
+// the machine is set up once, then only the window is moved for each of
+// the 26^rotor_count start positions (17,576 for three rotors, 456,976
+// for four), which is all `set_window` touch.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaStartPosition {
+    pub window: String,
+    pub score: f64,
+}
+
+pub struct EnigmaStartSearch<'a> {
+    machine: &'a mut EnigmaMachine,
+    // window the machine had, put back after every search
+    snapshot: String,
+}
+
+impl<'a> EnigmaStartSearch<'a> {
+    // machine with everything but the start position set up
+    pub fn new(machine: &'a mut EnigmaMachine) -> Self {
+        let snapshot = machine.get_window();
+        Self { machine, snapshot }
+    }
+
+    // every start position where the cipher text from `offset` decode to
+    // the crib. '?' in the crib match any letter, for a crib only partly known.
+    pub fn run_crib(&mut self, cipher_text: &str, crib: &str, offset: usize) -> Vec<String> {
+        let mut ret = vec![];
+        let known = crib.bytes().all(|b| b == b'?' || b.is_ascii_uppercase());
+        if !is_enigma_text(cipher_text) || !known || offset + crib.len() > cipher_text.len() {
+            return ret;
+        }
+
+        let cipher = &cipher_text.as_bytes()[offset..offset + crib.len()];
+        for window in self.windows() {
+            self.machine.set_window(&window);
+            (0..offset).for_each(|_| self.machine.step());
+            let matched = crib.bytes().zip(cipher.iter()).all(|(p, &c)| {
+                let decoded = self.machine.encode_charater(c as char) as u8;
+                p == b'?' || p == decoded
+            });
+            if matched {
+                ret.push(window);
+            }
+        }
+
+        self.machine.set_window(&self.snapshot);
+        ret
+    }
+
+    // the `top_n` start positions whose decrypt score best
+    pub fn run_fitness(
+        &mut self,
+        cipher_text: &str,
+        fitness: &dyn EnigmaFitness,
+        top_n: usize,
+    ) -> Vec<EnigmaStartPosition> {
+        let mut ret: Vec<EnigmaStartPosition> = vec![];
+        if !is_enigma_text(cipher_text) {
+            return ret;
+        }
+
+        for window in self.windows() {
+            self.machine.set_window(&window);
+            let score = fitness.score(&self.machine.encode_str(cipher_text));
+            keep_top(
+                &mut ret,
+                EnigmaStartPosition { window, score },
+                top_n,
+                |c| c.score,
+            );
+        }
+
+        self.machine.set_window(&self.snapshot);
+        ret
+    }
+
+    fn windows(&self) -> impl Iterator<Item = String> {
+        let rotor_count = self.machine.rotor_count();
+        (0..26_usize.pow(rotor_count as u32))
+            .map(move |idx| EnigmaMachine::window_from_index(idx, rotor_count))
+    }
+}
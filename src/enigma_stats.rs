@@ -0,0 +1,254 @@
+use std::{fs, path::Path, sync::OnceLock};
+
+use crate::enigma_types::*;
+
+// statistics of letters used to tell plain text from random letters.
+
+// languages with a model built in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnigmaLanguage {
+    German,
+    English,
+}
+
+impl EnigmaLanguage {
+    // how often each letter show up, in percent
+    pub fn letter_frequencies(&self) -> [f64; 26] {
+        match self {
+            Self::German => [
+                6.51, 1.89, 3.06, 5.08, 17.40, 1.66, 3.01, 4.76, 7.55, 0.27, 1.21, 3.44, 2.53,
+                9.78, 2.51, 0.79, 0.02, 7.00, 7.27, 6.15, 4.35, 0.67, 1.89, 0.03, 0.04, 1.13,
+            ],
+            Self::English => [
+                8.167, 1.492, 2.782, 4.253, 12.702, 2.228, 2.015, 6.094, 6.966, 0.153, 0.772,
+                4.025, 2.406, 6.749, 7.507, 1.929, 0.095, 5.987, 6.327, 9.056, 2.758, 0.978, 2.360,
+                0.150, 1.974, 0.074,
+            ],
+        }
+    }
+
+    // sample text the built-in n-gram models are trained on.
+    // it is only about 40 lines, enough for single letters and bigrams.
+    // most trigrams and quadgrams never show up in it and get the floor,
+    // so those models are rough, train on a real corpus for better ones.
+    pub fn corpus(&self) -> &'static str {
+        match self {
+            Self::German => include_str!("corpus/german.txt"),
+            Self::English => include_str!("corpus/english.txt"),
+        }
+    }
+}
+
+// how many times each letter appear, anything not 'A' to 'Z' is skipped
+pub fn letter_counts(s: &str) -> [usize; 26] {
+    let mut ret = [0; 26];
+    s.bytes()
+        .filter(|b| b.is_ascii_uppercase())
+        .for_each(|b| ret[(b - b'A') as usize] += 1);
+    ret
+}
+
+// chance that two letters picked from the text are the same.
+// random text give about 1/26 = 0.0385, german about 0.076.
+pub fn index_of_coincidence(s: &str) -> f64 {
+    index_of_coincidence_counts(&letter_counts(s))
+}
+
+pub fn index_of_coincidence_counts(counts: &[usize; 26]) -> f64 {
+    let total = counts.iter().sum::<usize>();
+    if total < 2 {
+        return 0.0;
+    }
+    let same = counts
+        .iter()
+        .map(|&n| n * n.saturating_sub(1))
+        .sum::<usize>();
+    same as f64 / (total * (total - 1)) as f64
+}
+
+// share of each letter in the text, in percent
+pub fn letter_frequencies(s: &str) -> [f64; 26] {
+    let counts = letter_counts(s);
+    let total = counts.iter().sum::<usize>().max(1) as f64;
+    let mut ret = [0.0; 26];
+    ret.iter_mut()
+        .zip(counts.iter())
+        .for_each(|(f, &n)| *f = n as f64 * 100.0 / total);
+    ret
+}
+
+// how far the letter counts are from what the language expect,
+// 0 is a perfect match and random text give a large number.
+// a text with no letter match nothing, it give infinity.
+pub fn chi_squared(s: &str, expected: &[f64; 26]) -> f64 {
+    let counts = letter_counts(s);
+    let total = counts.iter().sum::<usize>() as f64;
+    if total == 0.0 {
+        return f64::INFINITY;
+    }
+    let sum = expected.iter().sum::<f64>();
+    counts
+        .iter()
+        .zip(expected.iter())
+        .filter(|&(_, &e)| e > 0.0)
+        .map(|(&n, &e)| {
+            let e = e / sum * total;
+            (n as f64 - e).powi(2) / e
+        })
+        .sum()
+}
+
+// anything that can tell how much a decrypt look like plain text,
+// higher is better. attacks take it as a parameter so scorers can be swapped.
+pub trait EnigmaFitness {
+    fn score(&self, text: &str) -> f64;
+}
+
+// index of coincidence as fitness, good when the decrypt is still mostly wrong
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EnigmaIocFitness;
+
+// a shared model, e.g. a built-in n-gram model, score like the model
+impl<T: EnigmaFitness + ?Sized> EnigmaFitness for &T {
+    fn score(&self, text: &str) -> f64 {
+        (**self).score(text)
+    }
+}
+
+impl EnigmaFitness for EnigmaIocFitness {
+    fn score(&self, text: &str) -> f64 {
+        index_of_coincidence(text)
+    }
+}
+
+// chi-squared against a language, negated so higher is better
+#[derive(Clone, Copy, Debug)]
+pub struct EnigmaChiSquaredFitness {
+    expected: [f64; 26],
+}
+
+impl EnigmaChiSquaredFitness {
+    pub fn new(expected: &[f64; 26]) -> Self {
+        Self {
+            expected: *expected,
+        }
+    }
+
+    pub fn new_with_language(language: EnigmaLanguage) -> Self {
+        Self::new(&language.letter_frequencies())
+    }
+}
+
+impl EnigmaFitness for EnigmaChiSquaredFitness {
+    fn score(&self, text: &str) -> f64 {
+        -chi_squared(text, &self.expected)
+    }
+}
+
+// log probability of every n letters in a row,
+// averaged so texts of different length can be compared
+#[derive(Clone, Debug)]
+pub struct EnigmaNgramFitness {
+    n: usize,
+    log_probs: Vec<f64>,
+    floor: f64,
+}
+
+impl EnigmaNgramFitness {
+    // count n-grams in a plain text corpus, anything not 'A' to 'Z' is skipped.
+    // n is 1 to 4, a quadgram table already hold 456976 numbers
+    pub fn train(n: usize, corpus: &str) -> Result<Self, EnigmaNgramError> {
+        if !(1..=4).contains(&n) {
+            return Err(EnigmaNgramError::InvalidLength(n));
+        }
+        let letters = corpus
+            .bytes()
+            .filter_map(|b| {
+                let b = b.to_ascii_uppercase();
+                b.is_ascii_uppercase().then(|| (b - b'A') as usize)
+            })
+            .collect::<Vec<usize>>();
+
+        let mut counts = vec![0_usize; 26_usize.pow(n as u32)];
+        letters
+            .windows(n)
+            .for_each(|w| counts[Self::index_of(w)] += 1);
+        let total = counts.iter().sum::<usize>().max(1) as f64;
+
+        // n-gram never seen still get a small chance
+        let floor = (0.01 / total).log10();
+        Ok(Self {
+            n,
+            log_probs: counts
+                .iter()
+                .map(|&c| {
+                    if c == 0 {
+                        floor
+                    } else {
+                        (c as f64 / total).log10()
+                    }
+                })
+                .collect(),
+            floor,
+        })
+    }
+
+    // train on a plain text file, e.g. a book saved from the net
+    pub fn train_from_file<P: AsRef<Path>>(n: usize, path: P) -> Result<Self, EnigmaNgramError> {
+        let corpus = fs::read_to_string(path).map_err(EnigmaNgramError::Io)?;
+        Self::train(n, &corpus)
+    }
+
+    // models trained on the sample text that come with the crate, see
+    // `EnigmaLanguage::corpus`. each is trained the first time it is asked
+    // for and shared after that.
+    pub fn bigram(language: EnigmaLanguage) -> &'static Self {
+        Self::built_in(2, language)
+    }
+
+    pub fn trigram(language: EnigmaLanguage) -> &'static Self {
+        Self::built_in(3, language)
+    }
+
+    pub fn quadgram(language: EnigmaLanguage) -> &'static Self {
+        Self::built_in(4, language)
+    }
+
+    fn built_in(n: usize, language: EnigmaLanguage) -> &'static Self {
+        // bigram, trigram and quadgram of each language
+        static MODELS: [OnceLock<EnigmaNgramFitness>; 6] = [const { OnceLock::new() }; 6];
+        let idx = match language {
+            EnigmaLanguage::German => n - 2,
+            EnigmaLanguage::English => n + 1,
+        };
+        // n is 2 to 4 here, training can't fail
+        MODELS[idx].get_or_init(|| Self::train(n, language.corpus()).unwrap())
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    fn index_of(letters: &[usize]) -> usize {
+        letters.iter().fold(0, |acc, &l| acc * 26 + l)
+    }
+}
+
+impl EnigmaFitness for EnigmaNgramFitness {
+    fn score(&self, text: &str) -> f64 {
+        let letters = text
+            .bytes()
+            .filter(|b| b.is_ascii_uppercase())
+            .map(|b| (b - b'A') as usize)
+            .collect::<Vec<usize>>();
+        if letters.len() < self.n {
+            return self.floor;
+        }
+
+        let sum = letters
+            .windows(self.n)
+            .map(|w| self.log_probs[Self::index_of(w)])
+            .sum::<f64>();
+        sum / (letters.len() + 1 - self.n) as f64
+    }
+}
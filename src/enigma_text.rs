@@ -0,0 +1,280 @@
+use crate::enigma_types::*;
+
+// the keyboard only has 'A' to 'Z', so operators rewrote the text before
+// typing it in: punctuation became letter codes, CH became Q, numbers were
+// spelled out (army) or typed with the top row of the keyboard (navy).
+// decoding does the same backward, word by word, to get readable text.
+// CH in front of U is left as it is, QU could not be told from CHU.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnigmaNumberStyle {
+    // every digit as a german word, 1830 -> EINSAQTDREINULL
+    SpelledOut,
+    // top row of the keyboard between two Y, 1830 -> YQIEPY
+    TopRow,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnigmaTextConvention {
+    // written between words, a period followed by a space use it only once
+    pub space: &'static str,
+    // code for each punctuation mark, written as a word of its own
+    // unless it is the same as the space
+    pub punctuation: &'static [(char, &'static str)],
+    pub numbers: EnigmaNumberStyle,
+    // a name in double quote is bracketed with this
+    pub name_marker: &'static str,
+    // write CH as Q
+    pub ch_as_q: bool,
+}
+
+const SPELLED_DIGITS: [&str; 10] = [
+    "NULL", "EINS", "ZWO", "DREI", "VIER", "FUENF", "SEQS", "SIEBEN", "AQT", "NEUN",
+];
+
+// QWERTZ keyboard read as 1 to 9 then 0, indexed by digit so 0 is 'P'
+const TOP_ROW_DIGITS: [char; 10] = ['P', 'Q', 'W', 'E', 'R', 'T', 'Z', 'U', 'I', 'O'];
+
+impl EnigmaTextConvention {
+    pub const HEER: EnigmaTextConvention = EnigmaTextConvention {
+        space: "X",
+        punctuation: &[('.', "X"), (',', "ZZ"), ('?', "FRAGE")],
+        numbers: EnigmaNumberStyle::SpelledOut,
+        name_marker: "JJ",
+        ch_as_q: true,
+    };
+
+    pub const KRIEGSMARINE: EnigmaTextConvention = EnigmaTextConvention {
+        space: "X",
+        punctuation: &[('.', "X"), (',', "ZZ"), ('?', "UD"), ('-', "YY")],
+        numbers: EnigmaNumberStyle::TopRow,
+        name_marker: "JJ",
+        ch_as_q: true,
+    };
+
+    // turn ordinary text into something that can be typed on the machine
+    pub fn encode(&self, s: &str) -> Result<String, EnigmaMessageError> {
+        let chars = s
+            .trim()
+            .chars()
+            .flat_map(|c| c.to_uppercase())
+            .collect::<Vec<char>>();
+
+        let mut ret = String::new();
+        let mut word = String::new();
+        let mut number = String::new();
+        // a run of spaces is written once,
+        // and a period already separate the words
+        let period_is_space = self.punctuation_code('.') == Some(self.space);
+        let mut skip_space = false;
+        // a punctuation code is a word of its own, so what follow it need a space
+        let mut space_after = false;
+
+        for c in chars {
+            if !c.is_ascii_digit() && !number.is_empty() {
+                ret.push_str(&self.encode_number(&number));
+                number.clear();
+            }
+            if !c.is_ascii_uppercase() && !is_umlaut(c) {
+                ret.push_str(&self.encode_word(&word));
+                word.clear();
+            }
+            if space_after && !c.is_whitespace() {
+                ret.push_str(self.space);
+            }
+
+            match c {
+                'A'..='Z' => word.push(c),
+                'Ä' => word.push_str("AE"),
+                'Ö' => word.push_str("OE"),
+                'Ü' => word.push_str("UE"),
+                '0'..='9' => number.push(c),
+                '"' => ret.push_str(self.name_marker),
+                c if c.is_whitespace() => {
+                    if !skip_space {
+                        ret.push_str(self.space);
+                    }
+                }
+                c => {
+                    let code = self
+                        .punctuation
+                        .iter()
+                        .find(|(p, _)| *p == c)
+                        .map(|&(_, code)| code)
+                        .ok_or(EnigmaMessageError::InvalidText)?;
+                    if code != self.space && !ret.is_empty() && !ret.ends_with(self.space) {
+                        ret.push_str(self.space);
+                    }
+                    ret.push_str(code);
+                }
+            }
+            space_after = !c.is_whitespace()
+                && self
+                    .punctuation_code(c)
+                    .is_some_and(|code| code != self.space);
+            skip_space = c.is_whitespace() || (c == '.' && period_is_space);
+        }
+        ret.push_str(&self.encode_word(&word));
+        ret.push_str(&self.encode_number(&number));
+
+        Ok(ret)
+    }
+
+    // best effort to make a decrypt readable again.
+    // period and space share the same code, so a period only come back at the end.
+    pub fn decode(&self, s: &str) -> String {
+        let ends_with_period =
+            self.punctuation_code('.') == Some(self.space) && s.ends_with(self.space);
+        let s = if ends_with_period {
+            &s[..s.len() - self.space.len()]
+        } else {
+            s
+        };
+
+        let mut ret = String::new();
+        for token in s.split(self.space) {
+            // only a whole word is punctuation, it belong to the word before
+            let punctuation = self
+                .punctuation
+                .iter()
+                .find(|(_, code)| *code != self.space && *code == token);
+            match punctuation {
+                Some(&(p, _)) => ret.push(p),
+                None => {
+                    if !ret.is_empty() {
+                        ret.push(' ');
+                    }
+                    ret.push_str(&self.decode_token(token));
+                }
+            }
+        }
+        if ends_with_period {
+            ret.push('.');
+        }
+        ret
+    }
+
+    fn punctuation_code(&self, c: char) -> Option<&'static str> {
+        self.punctuation
+            .iter()
+            .find(|(p, _)| *p == c)
+            .map(|&(_, code)| code)
+    }
+
+    fn encode_word(&self, word: &str) -> String {
+        if !self.ch_as_q {
+            return word.to_owned();
+        }
+        let mut ret = String::new();
+        let mut rest = word;
+        while let Some(at) = rest.find("CH") {
+            ret.push_str(&rest[..at]);
+            if rest[at + 2..].starts_with('U') {
+                ret.push_str("CH");
+            } else {
+                ret.push('Q');
+            }
+            rest = &rest[at + 2..];
+        }
+        ret.push_str(rest);
+        ret
+    }
+
+    fn encode_number(&self, number: &str) -> String {
+        if number.is_empty() {
+            return String::new();
+        }
+        let digits = number.bytes().map(|b| (b - b'0') as usize);
+        match self.numbers {
+            EnigmaNumberStyle::SpelledOut => digits.map(|d| SPELLED_DIGITS[d]).collect(),
+            EnigmaNumberStyle::TopRow => {
+                let row = digits.map(|d| TOP_ROW_DIGITS[d]).collect::<String>();
+                format!("Y{}Y", row)
+            }
+        }
+    }
+
+    fn decode_token(&self, token: &str) -> String {
+        let mut token = token;
+
+        // names
+        let marker = self.name_marker;
+        let open = !marker.is_empty() && token.starts_with(marker);
+        if open {
+            token = &token[marker.len()..];
+        }
+        let close = !marker.is_empty() && token.ends_with(marker);
+        if close {
+            token = &token[..token.len() - marker.len()];
+        }
+
+        let mut ret = String::new();
+        if open {
+            ret.push('"');
+        }
+        match self.decode_number(token) {
+            Some(number) => ret.push_str(&number),
+            None => ret.push_str(&self.decode_word(token)),
+        }
+        if close {
+            ret.push('"');
+        }
+        ret
+    }
+
+    // a whole word that reads as a number
+    fn decode_number(&self, token: &str) -> Option<String> {
+        if token.is_empty() {
+            return None;
+        }
+        match self.numbers {
+            EnigmaNumberStyle::SpelledOut => {
+                let mut ret = String::new();
+                let mut rest = token;
+                while !rest.is_empty() {
+                    let d = SPELLED_DIGITS
+                        .iter()
+                        .position(|word| rest.starts_with(word))?;
+                    ret.push((d as u8 + b'0') as char);
+                    rest = &rest[SPELLED_DIGITS[d].len()..];
+                }
+                Some(ret)
+            }
+            EnigmaNumberStyle::TopRow => {
+                let row = token.strip_prefix('Y')?.strip_suffix('Y')?;
+                if row.is_empty() {
+                    return None;
+                }
+                row.chars()
+                    .map(|c| {
+                        TOP_ROW_DIGITS
+                            .iter()
+                            .position(|&t| t == c)
+                            .map(|d| (d as u8 + b'0') as char)
+                    })
+                    .collect::<Option<String>>()
+            }
+        }
+    }
+
+    fn decode_word(&self, token: &str) -> String {
+        if !self.ch_as_q {
+            return token.to_owned();
+        }
+        // QU is still QU, any other Q was a CH (CHU was never written as QU)
+        let chars = token.chars().collect::<Vec<char>>();
+        let mut ret = String::new();
+        for (idx, &c) in chars.iter().enumerate() {
+            if c == 'Q' && chars.get(idx + 1) != Some(&'U') {
+                ret.push_str("CH");
+            } else {
+                ret.push(c);
+            }
+        }
+        ret
+    }
+}
+
+fn is_umlaut(c: char) -> bool {
+    matches!(c, 'Ä' | 'Ö' | 'Ü')
+}
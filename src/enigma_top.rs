@@ -0,0 +1,23 @@
+// searches keep only their best few candidates as they go.
+// `list` is kept best first: the candidate is put in order and the worst
+// one dropped when there are more than `top_n`.
+pub(crate) fn keep_top<T>(
+    list: &mut Vec<T>,
+    candidate: T,
+    top_n: usize,
+    score: impl Fn(&T) -> f64,
+) {
+    if list.len() >= top_n
+        && list
+            .last()
+            .is_none_or(|worst| score(worst) >= score(&candidate))
+    {
+        return;
+    }
+    let at = list
+        .iter()
+        .position(|c| score(c) < score(&candidate))
+        .unwrap_or(list.len());
+    list.insert(at, candidate);
+    list.truncate(top_n);
+}
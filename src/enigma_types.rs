@@ -1,4 +1,5 @@
 // use to define where two wire is connected in plug board.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct EnigmaPlugBoardWire(pub char, pub char);
 
 #[derive(Clone, Copy)]
@@ -23,6 +24,175 @@ pub enum EnigmaPlugBoardError {
     AlreadyHaveWire(EnigmaPlugBoardWire),
 }
 
+#[derive(Debug, PartialEq)]
+pub enum EnigmaMessageError {
+    // header line is missing or not in `time = count = grundstellung key =` form
+    InvalidHeader,
+    // a key is not made of 'A' to 'Z' or doesn't fit the machine
+    InvalidKey,
+    // kenngruppe should be exactly five letters
+    InvalidKenngruppe,
+    // text contain something that cannot be typed on the machine
+    InvalidText,
+    // letter count in the header doesn't match the letters received
+    LetterCountMismatch { expected: usize, found: usize },
+    // bigram table has no entry for this pair of letters
+    BigramNotInTable(String),
+    // indicator repeated at the end of a naval message is not the same as the start
+    IndicatorMismatch,
+    // a part of a multi-part message is not there
+    MissingPart(usize),
+    // part length leave no room for text after the kenngruppe
+    InvalidPartLength,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaRingSearchError {
+    // cipher text contain something other than 'A' to 'Z'
+    InvalidText,
+    // window should give every rotor a letter from 'A' to 'Z'
+    InvalidWindow,
+    // plug wire clash with one already on the board
+    InvalidWire(EnigmaPlugBoardWire),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaBombeError {
+    // cipher text or crib contain something other than 'A' to 'Z'
+    InvalidText,
+    // crib doesn't fit in the cipher text at that offset
+    CribOutOfRange,
+    // crib letter is the same as the cipher letter at this position,
+    // enigma never encode a letter to itself, so the crib cannot be there
+    CribClash(usize),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaRoddingError {
+    // cipher text or crib contain something other than 'A' to 'Z'
+    InvalidText,
+    // crib doesn't fit in the cipher text at that offset
+    CribOutOfRange,
+    // crib letter is the same as the cipher letter at this position
+    CribClash(usize),
+    // texts to compare for isomorphs should be the same length
+    LengthMismatch,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaBigramTableError {
+    // a bigram should be exactly two letters from 'A' to 'Z'
+    InvalidBigram,
+    // indicate that one of the bigram is already paired with another one.
+    AlreadyHavePair(String, String),
+}
+
+#[derive(Debug)]
+pub enum EnigmaNgramError {
+    // n-grams are counted for n from 1 to 4, longer ones need 26^n counts
+    InvalidLength(usize),
+    // corpus file can't be read
+    Io(std::io::Error),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaCyclometerError {
+    // doubled indicator should be exactly six letters from 'A' to 'Z'
+    InvalidIndicator,
+    // two indicators send the same letter to different letters,
+    // so they cannot come from the same day key
+    Inconsistent(usize),
+    // not every letter show up yet, need more indicators of the day
+    Incomplete,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaRecoveryError {
+    // plain or cipher text contain something other than 'A' to 'Z'
+    InvalidText,
+    // plain and cipher text should be the same length
+    LengthMismatch,
+    // window should give every rotor a letter from 'A' to 'Z'
+    InvalidWindow,
+    // ring should give every rotor a letter from 'A' to 'Z'
+    InvalidRing,
+    // plug wire clash with one already on the board
+    InvalidWire(EnigmaPlugBoardWire),
+    // these letters are never touched by the text, so cannot be told
+    Underdetermined(Vec<char>),
+    // pairs found at different positions don't agree with each other
+    Conflict(Vec<EnigmaReflectorConflict>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaKeyError {
+    // rotor order should be rotor numbers split by ','
+    InvalidRotorOrder,
+    // ring and window should give every rotor a letter from 'A' to 'Z'
+    InvalidLetters,
+    // plug wire should be two different letters from 'A' to 'Z'
+    InvalidWire,
+    // keys used together should have the same number of rotors
+    RotorCountMismatch,
+    // rotor order point past the rotors given, or to one that is wired wrong
+    InvalidRotor(usize),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaCheckpointError {
+    // a field is not there, or not where it is saved
+    MissingField(String),
+    // value of the field can't be read as a number
+    InvalidValue(String),
+    // key saved in the field can't be read, or can't be used with the rotors
+    InvalidKey(String, EnigmaKeyError),
+    // checkpoint file can't be read, or no file is set
+    Io(std::io::ErrorKind),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnigmaWorkFileError {
+    // first line doesn't name the kind of file expected, or the version is unknown
+    InvalidFormat,
+    // a field that must be there is not
+    MissingField(String),
+    // line that can't be read, with its line number
+    InvalidLine(usize),
+}
+
+// pair found at `position` clash with the pair found earlier at `earlier`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaReflectorConflict {
+    pub position: usize,
+    pub pair: (char, char),
+    pub earlier: usize,
+    pub earlier_pair: (char, char),
+}
+
+impl EnigmaRotorSetting<'_> {
+    // every way to put `slots` different rotors out of `available` into the machine,
+    // each order is a list of index into the available rotors
+    pub fn wheel_orders(available: usize, slots: usize) -> Vec<Vec<usize>> {
+        let mut ret = vec![vec![]];
+        for _ in 0..slots {
+            ret = ret
+                .into_iter()
+                .flat_map(|order: Vec<usize>| {
+                    (0..available)
+                        .filter(|idx| !order.contains(idx))
+                        .map(|idx| {
+                            let mut next = order.clone();
+                            next.push(idx);
+                            next
+                        })
+                        .collect::<Vec<Vec<usize>>>()
+                })
+                .collect();
+        }
+        ret
+    }
+}
+
 // some rotor setting can be use
 impl EnigmaRotorSetting<'static> {
     pub const I: EnigmaRotorSetting<'static> = EnigmaRotorSetting {
@@ -51,4 +221,43 @@ impl EnigmaRotorSetting<'static> {
         window: 'A',
         notch: 'V',
     };
+
+    pub const IV: EnigmaRotorSetting<'static> = EnigmaRotorSetting {
+        wiring: EnigmaRotorWiring {
+            forward: "ESOVPZJAYQUIRHXLNFTGKDCMWB",
+            backward: "HZWVARTNLGUPXQCEJMBSKDYOIF",
+        },
+        window: 'A',
+        notch: 'J',
+    };
+
+    pub const V: EnigmaRotorSetting<'static> = EnigmaRotorSetting {
+        wiring: EnigmaRotorWiring {
+            forward: "VZBRGITYUPSDNHLXAWMJQOFECK",
+            backward: "QCYLXWENFTZOSMVJUDKGIARPHB",
+        },
+        window: 'A',
+        notch: 'Z',
+    };
+
+    // greek wheels of the naval M4, they only go in the slow end beside a
+    // thin reflector. they are past `EnigmaMachine::STEPPING_ROTORS` so
+    // never move, and nothing come after them, so the notch does nothing.
+    pub const BETA: EnigmaRotorSetting<'static> = EnigmaRotorSetting {
+        wiring: EnigmaRotorWiring {
+            forward: "LEYJVCNIXWPBQMDRTAKZGFUHOS",
+            backward: "RLFOBVUXHDSANGYKMPZQWEJICT",
+        },
+        window: 'A',
+        notch: 'A',
+    };
+
+    pub const GAMMA: EnigmaRotorSetting<'static> = EnigmaRotorSetting {
+        wiring: EnigmaRotorWiring {
+            forward: "FSOKANUERHMBTIYCWLQPZXVGJD",
+            backward: "ELPZHAXJNYDRKFCTSIBMGWQVOU",
+        },
+        window: 'A',
+        notch: 'A',
+    };
 }
@@ -0,0 +1,244 @@
+use crate::{enigma_component::*, enigma_machine::*, enigma_procedure::*, enigma_types::*};
+
+// recover the wiring of an unknown fast rotor from known plain text,
+// with everything else about the machine known.
+// the rest of the machine (other rotors and reflector) at each position
+// is a known swap U, and the fast rotor at offset k is F(x) = W(x + k) - k.
+// for plain letter x over cipher letter y (after the plugboard)
+//
+//     U(W(x + k) - k) = W(y + k) - k
+//
+// so once W of one letter is known, the texts give W of many others.
+// guess W of one letter, follow the equations, and back out when two
+// letters end up wired to the same contact.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaRecoveredWiring {
+    pub forward: String,
+    pub backward: String,
+}
+
+pub struct EnigmaWiringRecovery<'a> {
+    // known rotors after the fast one
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    wires: Vec<EnigmaPlugBoardWire>,
+    // notch of the unknown rotor, try each one if it is not known
+    notch: char,
+    window: String,
+}
+
+// one equation, W(b) = U(W(a) - k) + k and the other way round
+struct EnigmaWiringLink {
+    a: usize,
+    b: usize,
+    offset: usize,
+    inner: [usize; 26],
+}
+
+impl EnigmaRecoveredWiring {
+    // setting for the rotor, e.g. to put it in a machine
+    pub fn setting(&self, notch: char) -> EnigmaRotorSetting<'_> {
+        EnigmaRotorSetting {
+            wiring: EnigmaRotorWiring {
+                forward: &self.forward,
+                backward: &self.backward,
+            },
+            notch,
+            window: 'A',
+        }
+    }
+
+    // wiring checked by the rotor itself
+    pub fn rotor(&self) -> Result<EnigmaRotor, EnigmaRotorWireError> {
+        let mut ret = EnigmaRotor::new();
+        ret.set_rotor_wiring_with_str(&self.forward, &self.backward)?;
+        Ok(ret)
+    }
+}
+
+impl<'a> EnigmaWiringRecovery<'a> {
+    pub fn new(
+        rotors: &[EnigmaRotorSetting<'a>],
+        reflector: &'a str,
+        wires: &[EnigmaPlugBoardWire],
+        notch: char,
+        window: &str,
+    ) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            wires: wires.to_vec(),
+            notch,
+            window: window.to_owned(),
+        }
+    }
+
+    // every wiring the texts agree with, usually just one
+    pub fn run(
+        &self,
+        plain_text: &str,
+        cipher_text: &str,
+    ) -> Result<Vec<EnigmaRecoveredWiring>, EnigmaRecoveryError> {
+        if !is_enigma_text(plain_text) || !is_enigma_text(cipher_text) {
+            return Err(EnigmaRecoveryError::InvalidText);
+        }
+        if plain_text.len() != cipher_text.len() {
+            return Err(EnigmaRecoveryError::LengthMismatch);
+        }
+
+        let links = self.links(plain_text, cipher_text)?;
+        let mut touched = [false; 26];
+        links.iter().for_each(|link| {
+            touched[link.a] = true;
+            touched[link.b] = true;
+        });
+        let free = touched
+            .iter()
+            .enumerate()
+            .filter(|&(_, &t)| !t)
+            .map(|(idx, _)| (idx as u8 + b'A') as char)
+            .collect::<Vec<char>>();
+        // a single letter left over can only go to the contact left over
+        if free.len() > 1 {
+            return Err(EnigmaRecoveryError::Underdetermined(free));
+        }
+
+        let mut ret = vec![];
+        Self::search(&links, [26; 26], &mut ret);
+        Ok(ret
+            .into_iter()
+            .map(|wiring| Self::complete(&wiring))
+            .filter(|wiring| wiring.rotor().is_ok())
+            .collect())
+    }
+
+    // the swap U and the offset of the fast rotor at every letter
+    fn links(
+        &self,
+        plain_text: &str,
+        cipher_text: &str,
+    ) -> Result<Vec<EnigmaWiringLink>, EnigmaRecoveryError> {
+        // a straight through rotor in the fast place leave just U
+        let straight = EnigmaRotorSetting {
+            wiring: EnigmaRotorWiring {
+                forward: EnigmaEntryWheel::ABC,
+                backward: EnigmaEntryWheel::ABC,
+            },
+            notch: self.notch,
+            window: 'A',
+        };
+        let mut settings = vec![straight];
+        settings.extend(self.rotors.iter().copied());
+        if self.window.len() != settings.len() || !is_enigma_text(&self.window) {
+            return Err(EnigmaRecoveryError::InvalidWindow);
+        }
+
+        let mut machine = EnigmaMachine::new_with_all_setting(settings, self.reflector);
+        let mut plug_board = EnigmaPlugBoard::new();
+        for &wire in self.wires.iter() {
+            if plug_board.add_wire(wire).is_err() {
+                return Err(EnigmaRecoveryError::InvalidWire(wire));
+            }
+        }
+        machine.set_window(&self.window);
+
+        Ok(plain_text
+            .bytes()
+            .zip(cipher_text.bytes())
+            .map(|(p, c)| {
+                machine.step();
+                let offset = (machine.get_window().as_bytes()[0] - b'A') as usize;
+                let x = plug_board.encode_index((p - b'A') as usize);
+                let y = plug_board.encode_index((c - b'A') as usize);
+                EnigmaWiringLink {
+                    a: (x + offset) % 26,
+                    b: (y + offset) % 26,
+                    offset,
+                    inner: machine.scramble_table(),
+                }
+            })
+            .collect())
+    }
+
+    fn search(links: &[EnigmaWiringLink], wiring: [usize; 26], found: &mut Vec<[usize; 26]>) {
+        // guess the first letter not wired yet that the texts touch
+        let next = links
+            .iter()
+            .flat_map(|link| [link.a, link.b])
+            .find(|&letter| wiring[letter] == 26);
+        let letter = match next {
+            Some(letter) => letter,
+            None => {
+                found.push(wiring);
+                return;
+            }
+        };
+
+        for contact in 0..26 {
+            if wiring.contains(&contact) {
+                continue;
+            }
+            let mut next = wiring;
+            if Self::propagate(links, &mut next, letter, contact) {
+                Self::search(links, next, found);
+            }
+        }
+    }
+
+    // wire `letter` to `contact` and everything that follow from it
+    fn propagate(
+        links: &[EnigmaWiringLink],
+        wiring: &mut [usize; 26],
+        letter: usize,
+        contact: usize,
+    ) -> bool {
+        let mut queue = vec![(letter, contact)];
+        while let Some((letter, contact)) = queue.pop() {
+            if wiring[letter] == contact {
+                continue;
+            }
+            if wiring[letter] != 26 || wiring.contains(&contact) {
+                return false;
+            }
+            wiring[letter] = contact;
+
+            for link in links {
+                let other = if link.a == letter {
+                    link.b
+                } else if link.b == letter {
+                    link.a
+                } else {
+                    continue;
+                };
+                let out = link.inner[(contact + 26 - link.offset) % 26];
+                queue.push((other, (out + link.offset) % 26));
+            }
+        }
+        true
+    }
+
+    // fill in the letter never touched, if there is one
+    fn complete(wiring: &[usize; 26]) -> EnigmaRecoveredWiring {
+        let mut wiring = *wiring;
+        let unused = (0..26).find(|contact| !wiring.contains(contact));
+        if let (Some(letter), Some(contact)) = (wiring.iter().position(|&w| w == 26), unused) {
+            wiring[letter] = contact;
+        }
+
+        let mut backward = [0; 26];
+        wiring
+            .iter()
+            .enumerate()
+            .for_each(|(letter, &contact)| backward[contact % 26] = letter);
+        let to_string = |v: &[usize]| {
+            v.iter()
+                .map(|&idx| (idx as u8 + b'A') as char)
+                .collect::<String>()
+        };
+        EnigmaRecoveredWiring {
+            forward: to_string(&wiring),
+            backward: to_string(&backward),
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    enigma_machine::*, enigma_parallel::*, enigma_procedure::*, enigma_stats::*, enigma_top::*,
+    enigma_types::*,
+};
+
+// a long search cut into work units that can be carried to other machines
+// on a stick and run there with nothing but this crate, the way volunteers
+// once broke the last naval messages.
+// a unit hold everything needed to run it: rotor wirings, reflector,
+// cipher text, how to score, and its slice of the key space.
+// both files are plain text, one `name value` line for each field.
+//
+//     enigma-work-unit 1
+//     id 0
+//     total 8
+//     reflector YRUHQSLDPXNGOKMIEBFZCWVJAT
+//     rotor EKMFLGDQVZNTOWYHXUSPAIBRCJ UWYGADFPVZBECKMTHXSLRINQOJ Q
+//     scoring bigram german
+//     top 10
+//     cipher QBLTWLDAHHYEOEFPTWYB
+//     item 0 676 0,1,2 AAA AAA
+//
+//     enigma-work-result 1
+//     id 0
+//     total 8
+//     complete true
+//     key -2.0318 0,1,2 AAA QDK
+
+const UNIT_HEADER: &str = "enigma-work-unit 1";
+const RESULT_HEADER: &str = "enigma-work-result 1";
+
+// how a decrypt is scored, see `EnigmaFitness`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnigmaScoring {
+    Ioc,
+    ChiSquared(EnigmaLanguage),
+    // built-in models, see `EnigmaNgramFitness::bigram`
+    Bigram(EnigmaLanguage),
+    Trigram(EnigmaLanguage),
+    Quadgram(EnigmaLanguage),
+}
+
+// a rotor that own its wiring, to be read back from a file
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaWorkRotor {
+    pub forward: String,
+    pub backward: String,
+    pub notch: char,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaWorkUnit {
+    pub id: usize,
+    // units the search was split into
+    pub total: usize,
+    pub reflector: String,
+    // what the rotor order of the items index into
+    pub rotors: Vec<EnigmaWorkRotor>,
+    pub scoring: EnigmaScoring,
+    pub top_n: usize,
+    pub cipher_text: String,
+    pub items: Vec<EnigmaWorkItem>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaWorkResult {
+    pub id: usize,
+    pub total: usize,
+    // false when the run was cancelled part way
+    pub complete: bool,
+    pub keys: Vec<EnigmaScoredKey>,
+}
+
+impl EnigmaScoring {
+    pub fn fitness(&self) -> Box<dyn EnigmaFitness + Sync> {
+        match *self {
+            Self::Ioc => Box::new(EnigmaIocFitness),
+            Self::ChiSquared(language) => {
+                Box::new(EnigmaChiSquaredFitness::new_with_language(language))
+            }
+            Self::Bigram(language) => Box::new(EnigmaNgramFitness::bigram(language)),
+            Self::Trigram(language) => Box::new(EnigmaNgramFitness::trigram(language)),
+            Self::Quadgram(language) => Box::new(EnigmaNgramFitness::quadgram(language)),
+        }
+    }
+
+    // back from the `Display` form
+    pub fn parse(s: &str) -> Option<Self> {
+        let language = |name: &str| match name {
+            "german" => Some(EnigmaLanguage::German),
+            "english" => Some(EnigmaLanguage::English),
+            _ => None,
+        };
+        match s.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["ioc"] => Some(Self::Ioc),
+            ["chi", name] => Some(Self::ChiSquared(language(name)?)),
+            ["bigram", name] => Some(Self::Bigram(language(name)?)),
+            ["trigram", name] => Some(Self::Trigram(language(name)?)),
+            ["quadgram", name] => Some(Self::Quadgram(language(name)?)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EnigmaScoring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let language = |language: &EnigmaLanguage| match language {
+            EnigmaLanguage::German => "german",
+            EnigmaLanguage::English => "english",
+        };
+        match self {
+            Self::Ioc => write!(f, "ioc"),
+            Self::ChiSquared(l) => write!(f, "chi {}", language(l)),
+            Self::Bigram(l) => write!(f, "bigram {}", language(l)),
+            Self::Trigram(l) => write!(f, "trigram {}", language(l)),
+            Self::Quadgram(l) => write!(f, "quadgram {}", language(l)),
+        }
+    }
+}
+
+impl EnigmaWorkRotor {
+    pub fn new(setting: &EnigmaRotorSetting) -> Self {
+        Self {
+            forward: setting.wiring.forward.to_owned(),
+            backward: setting.wiring.backward.to_owned(),
+            notch: setting.notch,
+        }
+    }
+
+    pub fn setting(&self) -> EnigmaRotorSetting<'_> {
+        EnigmaRotorSetting {
+            wiring: EnigmaRotorWiring {
+                forward: &self.forward,
+                backward: &self.backward,
+            },
+            notch: self.notch,
+            window: 'A',
+        }
+    }
+}
+
+impl EnigmaWorkUnit {
+    // cut the items into `units` units of about the same size
+    pub fn split(
+        rotors: &[EnigmaRotorSetting],
+        reflector: &str,
+        cipher_text: &str,
+        scoring: EnigmaScoring,
+        top_n: usize,
+        items: &[EnigmaWorkItem],
+        units: usize,
+    ) -> Vec<Self> {
+        let per_unit = items.len().div_ceil(units.max(1)).max(1);
+        let chunks = items.chunks(per_unit).collect::<Vec<&[EnigmaWorkItem]>>();
+        let total = chunks.len();
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(id, chunk)| Self {
+                id,
+                total,
+                reflector: reflector.to_owned(),
+                rotors: rotors.iter().map(EnigmaWorkRotor::new).collect(),
+                scoring,
+                top_n,
+                cipher_text: cipher_text.to_owned(),
+                items: chunk.to_vec(),
+            })
+            .collect()
+    }
+
+    // run the unit on every core of this machine
+    pub fn process(
+        &self,
+        progress: &mut dyn FnMut(&EnigmaSearchProgress),
+        cancel: &AtomicBool,
+    ) -> EnigmaWorkResult {
+        let settings = self
+            .rotors
+            .iter()
+            .map(|rotor| rotor.setting())
+            .collect::<Vec<EnigmaRotorSetting>>();
+        let search = EnigmaParallelSearch::new(&settings, &self.reflector, self.top_n);
+        let fitness = self.scoring.fitness();
+
+        let mut done = 0;
+        let keys = search.run(
+            &self.cipher_text,
+            &self.items,
+            fitness.as_ref(),
+            &mut |p| {
+                done = p.done;
+                progress(p);
+            },
+            cancel,
+        );
+        EnigmaWorkResult {
+            id: self.id,
+            total: self.total,
+            // an item cut short by the cancel is still sent back
+            complete: done == self.items.len() && !cancel.load(Ordering::Relaxed),
+            keys,
+        }
+    }
+
+    pub fn save(&self) -> String {
+        let mut ret = format!(
+            "{}\nid {}\ntotal {}\nreflector {}\n",
+            UNIT_HEADER, self.id, self.total, self.reflector
+        );
+        self.rotors.iter().for_each(|rotor| {
+            ret += &format!(
+                "rotor {} {} {}\n",
+                rotor.forward, rotor.backward, rotor.notch
+            );
+        });
+        ret += &format!(
+            "scoring {}\ntop {}\ncipher {}\n",
+            self.scoring, self.top_n, self.cipher_text
+        );
+        self.items.iter().for_each(|item| {
+            ret += &format!("item {} {} {}\n", item.first, item.count, item.key);
+        });
+        ret
+    }
+
+    pub fn load(s: &str) -> Result<Self, EnigmaWorkFileError> {
+        let mut id = None;
+        let mut total = None;
+        let mut reflector = None;
+        let mut rotors = vec![];
+        let mut scoring = None;
+        let mut top_n = None;
+        let mut cipher_text = None;
+        let mut items = vec![];
+
+        for (no, name, value) in fields(s, UNIT_HEADER)? {
+            let invalid = EnigmaWorkFileError::InvalidLine(no);
+            match name {
+                "id" => id = Some(value.parse::<usize>().map_err(|_| invalid)?),
+                "total" => total = Some(value.parse::<usize>().map_err(|_| invalid)?),
+                "reflector" if is_wiring(value) => reflector = Some(value.to_owned()),
+                "rotor" => match value.split(' ').collect::<Vec<&str>>().as_slice() {
+                    &[forward, backward, notch]
+                        if is_wiring(forward)
+                            && is_wiring(backward)
+                            && notch.len() == 1
+                            && is_enigma_text(notch) =>
+                    {
+                        rotors.push(EnigmaWorkRotor {
+                            forward: forward.to_owned(),
+                            backward: backward.to_owned(),
+                            notch: notch.chars().next().ok_or(invalid)?,
+                        })
+                    }
+                    _ => return Err(invalid),
+                },
+                "scoring" => scoring = Some(EnigmaScoring::parse(value).ok_or(invalid)?),
+                "top" => top_n = Some(value.parse::<usize>().map_err(|_| invalid)?),
+                "cipher" if is_enigma_text(value) => cipher_text = Some(value.to_owned()),
+                "item" => {
+                    let mut parts = value.splitn(3, ' ');
+                    let mut number = || parts.next().and_then(|p| p.parse::<usize>().ok());
+                    let (first, count) = (number(), number());
+                    let key = parts.next().and_then(|p| EnigmaKey::parse(p).ok());
+                    match (first, count, key) {
+                        // rotors come first, the order must point to one of them,
+                        // and the start positions must be ones the rotors have
+                        (Some(first), Some(count), Some(key))
+                            if key.rotor_order.iter().all(|&r| r < rotors.len())
+                                && key.ring.len() == key.rotor_order.len()
+                                && key.window.len() == key.rotor_order.len()
+                                && first.checked_add(count).is_some_and(|end| {
+                                    26_usize
+                                        .checked_pow(key.rotor_order.len() as u32)
+                                        .is_some_and(|positions| end <= positions)
+                                }) =>
+                        {
+                            items.push(EnigmaWorkItem { key, first, count })
+                        }
+                        _ => return Err(invalid),
+                    }
+                }
+                _ => return Err(invalid),
+            }
+        }
+
+        let missing = |name: &str| EnigmaWorkFileError::MissingField(name.to_owned());
+        Ok(Self {
+            id: id.ok_or_else(|| missing("id"))?,
+            total: total.ok_or_else(|| missing("total"))?,
+            reflector: reflector.ok_or_else(|| missing("reflector"))?,
+            rotors,
+            scoring: scoring.ok_or_else(|| missing("scoring"))?,
+            top_n: top_n.ok_or_else(|| missing("top"))?,
+            cipher_text: cipher_text.ok_or_else(|| missing("cipher"))?,
+            items,
+        })
+    }
+}
+
+impl EnigmaWorkResult {
+    // best keys of every result, best first.
+    // a unit run on two machines give the same keys twice, they are kept once.
+    pub fn merge(results: &[Self], top_n: usize) -> Vec<EnigmaScoredKey> {
+        let mut ret: Vec<EnigmaScoredKey> = vec![];
+        for candidate in results.iter().flat_map(|result| result.keys.iter()) {
+            if !ret.iter().any(|c| c.key == candidate.key) {
+                keep_top(&mut ret, candidate.clone(), top_n, |c| c.score);
+            }
+        }
+        ret
+    }
+
+    // units that have no complete result yet
+    pub fn missing(results: &[Self]) -> Vec<usize> {
+        let total = results.iter().map(|result| result.total).max().unwrap_or(0);
+        (0..total)
+            .filter(|&id| !results.iter().any(|r| r.id == id && r.complete))
+            .collect()
+    }
+
+    pub fn save(&self) -> String {
+        let mut ret = format!(
+            "{}\nid {}\ntotal {}\ncomplete {}\n",
+            RESULT_HEADER, self.id, self.total, self.complete
+        );
+        self.keys.iter().for_each(|candidate| {
+            ret += &format!("key {} {}\n", candidate.score, candidate.key);
+        });
+        ret
+    }
+
+    pub fn load(s: &str) -> Result<Self, EnigmaWorkFileError> {
+        let mut id = None;
+        let mut total = None;
+        let mut complete = None;
+        let mut keys = vec![];
+
+        for (no, name, value) in fields(s, RESULT_HEADER)? {
+            let invalid = EnigmaWorkFileError::InvalidLine(no);
+            match name {
+                "id" => id = Some(value.parse::<usize>().map_err(|_| invalid)?),
+                "total" => total = Some(value.parse::<usize>().map_err(|_| invalid)?),
+                "complete" => complete = Some(value.parse::<bool>().map_err(|_| invalid)?),
+                "key" => {
+                    let (score, key) = value.split_once(' ').ok_or(invalid)?;
+                    match (score.parse::<f64>(), EnigmaKey::parse(key)) {
+                        (Ok(score), Ok(key)) => keys.push(EnigmaScoredKey { key, score }),
+                        _ => return Err(EnigmaWorkFileError::InvalidLine(no)),
+                    }
+                }
+                _ => return Err(invalid),
+            }
+        }
+
+        let missing = |name: &str| EnigmaWorkFileError::MissingField(name.to_owned());
+        Ok(Self {
+            id: id.ok_or_else(|| missing("id"))?,
+            total: total.ok_or_else(|| missing("total"))?,
+            complete: complete.ok_or_else(|| missing("complete"))?,
+            keys,
+        })
+    }
+}
+
+// (line number, name, value) of every line after the header
+fn fields<'s>(
+    s: &'s str,
+    header: &str,
+) -> Result<Vec<(usize, &'s str, &'s str)>, EnigmaWorkFileError> {
+    let mut lines = s.lines().enumerate();
+    match lines.next() {
+        Some((_, first)) if first.trim() == header => {}
+        _ => return Err(EnigmaWorkFileError::InvalidFormat),
+    }
+    // a field saved with an empty value is only its name once trimmed
+    Ok(lines
+        .map(|(no, line)| (no, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(no, line)| {
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            (no + 1, name, value)
+        })
+        .collect())
+}
+
+// all 26 letters, each once
+fn is_wiring(s: &str) -> bool {
+    let mut seen = [false; 26];
+    s.len() == 26
+        && is_enigma_text(s)
+        && s.bytes()
+            .all(|b| !std::mem::replace(&mut seen[(b - b'A') as usize], true))
+}
@@ -0,0 +1,208 @@
+use std::{cmp::Reverse, fmt};
+
+use crate::{enigma_machine::*, enigma_procedure::*, enigma_types::*};
+
+// Zygalski sheets.
+// from late 1938 the ground setting was picked by the operator and sent
+// in clear before the doubled key, so the characteristic could no longer
+// be catalogued. what is left: some indicators have the same letter at
+// distance 3 (a "female"), and that can only happen where the product of
+// the two scramblers has a fixed point.
+// a sheet mark every position of the middle and fast rotor where that is
+// possible, one sheet for each position of the other rotors.
+// sheets are made with every ring at 'A', so the rotor cores are at
+// window - ring. stacking the sheets of each female shifted by its ground
+// setting, the ring settings where every female line up are left.
+// like the real sheets, a turnover of the middle rotor inside the
+// indicator is not told apart, so a female can now and then be missed.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaFemale {
+    // ground setting sent in clear before the indicator, fast rotor first
+    // like the window and `EnigmaMessageHeader.grundstellung`
+    pub ground: String,
+    // 0 for the 1st and 4th letter, 1 for the 2nd and 5th, 2 for the 3rd and 6th
+    pub pair: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaZygalskiSheet {
+    // index into the rotors given to the sheets
+    pub rotor_order: Vec<usize>,
+    // window of every rotor after the middle one
+    pub left: String,
+    // one bit for each pair, fast rotor change fastest
+    marks: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnigmaZygalskiStop {
+    pub rotor_order: Vec<usize>,
+    pub ring: String,
+    // females that line up at this ring setting
+    pub matches: usize,
+}
+
+pub struct EnigmaZygalskiSheets<'a> {
+    rotors: Vec<EnigmaRotorSetting<'a>>,
+    reflector: &'a str,
+    // females that are allowed not to line up
+    pub tolerance: usize,
+}
+
+impl EnigmaFemale {
+    // every female in one doubled indicator
+    pub fn from_indicator(ground: &str, indicator: &str) -> Vec<Self> {
+        if indicator.len() != 6 || !is_enigma_text(indicator) || !is_enigma_text(ground) {
+            return vec![];
+        }
+        let letters = indicator.as_bytes();
+        (0..3)
+            .filter(|&pair| letters[pair] == letters[pair + 3])
+            .map(|pair| Self {
+                ground: ground.to_owned(),
+                pair,
+            })
+            .collect()
+    }
+}
+
+impl EnigmaZygalskiSheet {
+    // can the pair give a female with the cores of middle and fast rotor here
+    pub fn is_marked(&self, middle: char, fast: char, pair: usize) -> bool {
+        let idx = (fast as u8 - b'A') as usize + (middle as u8 - b'A') as usize * 26;
+        self.marks[idx] & (1 << pair) != 0
+    }
+}
+
+// the sheet of the 1st and 4th letter,
+// a row for each middle rotor position, `O` where the hole is punched
+impl fmt::Display for EnigmaZygalskiSheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:?} {}", self.rotor_order, self.left)?;
+        for row in self.marks.chunks(26) {
+            let line = row
+                .iter()
+                .map(|&mark| if mark & 1 != 0 { 'O' } else { '.' })
+                .collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> EnigmaZygalskiSheets<'a> {
+    pub fn new(rotors: &[EnigmaRotorSetting<'a>], reflector: &'a str) -> Self {
+        Self {
+            rotors: rotors.to_vec(),
+            reflector,
+            tolerance: 0,
+        }
+    }
+
+    // every sheet of one wheel order, one for each position of the other rotors
+    pub fn sheets(&self, rotor_order: &[usize]) -> Vec<EnigmaZygalskiSheet> {
+        let marks = self.marks(rotor_order);
+        let others = rotor_order.len().saturating_sub(2);
+        marks
+            .chunks(26 * 26)
+            .enumerate()
+            .map(|(idx, marks)| EnigmaZygalskiSheet {
+                rotor_order: rotor_order.to_vec(),
+                left: EnigmaMachine::window_from_index(idx, others),
+                marks: marks.to_vec(),
+            })
+            .collect()
+    }
+
+    // every wheel order that can be made from the rotors given
+    pub fn search(&self, females: &[EnigmaFemale], rotor_count: usize) -> Vec<EnigmaZygalskiStop> {
+        let orders = EnigmaRotorSetting::wheel_orders(self.rotors.len(), rotor_count);
+        self.search_wheel_orders(females, &orders)
+    }
+
+    // stops with the most females lined up first
+    pub fn search_wheel_orders(
+        &self,
+        females: &[EnigmaFemale],
+        orders: &[Vec<usize>],
+    ) -> Vec<EnigmaZygalskiStop> {
+        let mut ret = vec![];
+        for order in orders {
+            if females
+                .iter()
+                .any(|female| female.ground.len() != order.len())
+            {
+                continue;
+            }
+            let marks = self.marks(order);
+            let grounds = females
+                .iter()
+                .map(|female| {
+                    female
+                        .ground
+                        .bytes()
+                        .map(|b| (b - b'A') as usize)
+                        .collect::<Vec<usize>>()
+                })
+                .collect::<Vec<Vec<usize>>>();
+
+            for idx in 0..26_usize.pow(order.len() as u32) {
+                let ring = EnigmaMachine::window_from_index(idx, order.len());
+                // shifting every sheet by the ring is the same as
+                // shifting the ground settings the other way
+                let shift = ring
+                    .bytes()
+                    .map(|b| (b - b'A') as usize)
+                    .collect::<Vec<usize>>();
+                let matches = females
+                    .iter()
+                    .zip(grounds.iter())
+                    .filter(|(female, ground)| {
+                        let core = ground
+                            .iter()
+                            .zip(shift.iter())
+                            .rev()
+                            .fold(0, |acc, (&g, &r)| acc * 26 + (g + 26 - r) % 26);
+                        marks[core] & (1 << female.pair) != 0
+                    })
+                    .count();
+
+                if matches + self.tolerance >= females.len() {
+                    ret.push(EnigmaZygalskiStop {
+                        rotor_order: order.clone(),
+                        ring,
+                        matches,
+                    });
+                }
+            }
+        }
+        ret.sort_by_key(|stop| Reverse(stop.matches));
+        ret
+    }
+
+    // female bits of every core position, numbered like `window_from_index`
+    fn marks(&self, rotor_order: &[usize]) -> Vec<u8> {
+        let settings = rotor_order
+            .iter()
+            .map(|&idx| self.rotors[idx])
+            .collect::<Vec<EnigmaRotorSetting>>();
+        let mut machine = EnigmaMachine::new_with_all_setting(settings, self.reflector);
+
+        (0..26_usize.pow(rotor_order.len() as u32))
+            .map(|idx| {
+                machine.set_window(&EnigmaMachine::window_from_index(idx, rotor_order.len()));
+                let tables = (0..6)
+                    .map(|_| {
+                        machine.step();
+                        machine.scramble_table()
+                    })
+                    .collect::<Vec<[usize; 26]>>();
+
+                (0..3)
+                    .filter(|&pair| (0..26).any(|c| tables[pair][c] == tables[pair + 3][c]))
+                    .fold(0, |acc, pair| acc | (1 << pair))
+            })
+            .collect()
+    }
+}
@@ -1,16 +1,109 @@
+// written before clippy was run on the crate, left as it is
+#[allow(clippy::len_zero, clippy::needless_return, clippy::useless_conversion)]
 mod enigma_machine;
 pub use enigma_machine::*;
 
 mod enigma_types;
 pub use enigma_types::*;
 
+#[allow(
+    clippy::assign_op_pattern,
+    clippy::map_identity,
+    clippy::needless_return,
+    clippy::new_without_default,
+    clippy::ptr_arg,
+    clippy::redundant_pattern_matching,
+    clippy::unnecessary_cast,
+    clippy::useless_conversion
+)]
 mod enigma_component;
 pub use enigma_component::*;
 
+mod enigma_procedure;
+pub use enigma_procedure::*;
+
+mod enigma_naval;
+pub use enigma_naval::*;
+
+mod enigma_format;
+pub use enigma_format::*;
+
+mod enigma_random;
+pub use enigma_random::*;
+
+mod enigma_text;
+pub use enigma_text::*;
+
+mod enigma_menu;
+pub use enigma_menu::*;
+
+mod enigma_bombe;
+pub use enigma_bombe::*;
+
+mod enigma_crib;
+pub use enigma_crib::*;
+
+mod enigma_stats;
+pub use enigma_stats::*;
+
+mod enigma_top;
+
+mod enigma_ioc_search;
+pub use enigma_ioc_search::*;
+
+mod enigma_plugboard_climb;
+pub use enigma_plugboard_climb::*;
+
+mod enigma_ring_search;
+pub use enigma_ring_search::*;
+
+mod enigma_cyclometer;
+pub use enigma_cyclometer::*;
+
+mod enigma_zygalski;
+pub use enigma_zygalski::*;
+
+mod enigma_rodding;
+pub use enigma_rodding::*;
+
+mod enigma_banburismus;
+pub use enigma_banburismus::*;
+
+mod enigma_depth;
+pub use enigma_depth::*;
+
+mod enigma_herivel;
+pub use enigma_herivel::*;
+
+mod enigma_wiring_recovery;
+pub use enigma_wiring_recovery::*;
+
+mod enigma_reflector_recovery;
+pub use enigma_reflector_recovery::*;
+
+mod enigma_start_search;
+pub use enigma_start_search::*;
+
+mod enigma_annealing;
+pub use enigma_annealing::*;
+
+mod enigma_parallel;
+pub use enigma_parallel::*;
+
+mod enigma_work_unit;
+pub use enigma_work_unit::*;
+
+mod enigma_batch;
+pub use enigma_batch::*;
+
 #[cfg(test)]
 mod test {
     use crate::{*};
 
+    // plain text for the attacks, written the way operators did
+    const GERMAN_TEXT: &str = "ANXOBERKOMMANDODERWEHRMAQTXBETREFFXLAGEBERIQTXDERFEINDHATINDERNAQTMITSTARKENKRAEFTENDENFLUSSUEBERSQRITTENUNDVERSUQTNUNDIESTADTVONNORDENHERZUNEHMENXDIEEIGENENTRUPPENHALTENDIEBISHERIGESTELLUNGUNDWERDENDURQEINEVERSTAERKTEDIVISIONUNTERSTUETZTXMUNITIONUNDVERPFLEGUNGSINDFUERDREITAGEVORHANDENXDASWETTERISTKLARDIESIQTWEITEGUTXDERANGRIFFBEGINNTMORGENFRUEHUMFUENFUHRDREISSIGXALLEEINHEITENMELDENIHREBEREITSQAFTBISMITTERNAQTXGEZXKOMMANDIERENDERGENERAL";
+    const WEATHER_TEXT: &str = "WETTERVORHERSAGEFUERDENBEREIQDERHEERESGRUPPENORDXIMLAUFEDESTAGESZUNEHMENDEBEWOELKUNGAUSWESTENMITEINZELNENREGENSQAUERNXDERWINDDREHTAUFNORDWESTUNDFRISQTSTARKAUFXDIETEMPERATURENLIEGENZWISQENFUENFUNDZEHNGRADXINDERNAQTISTMITBODENFROSTZUREQNENXDIESIQTWEITEBETRAEGTZEHNKILOMETERUNDVERSQLEQTERTSIQGEGENABENDXFLUGBETRIEBISTBISMITTAGMOEGLIQ";
+
     #[test]
     fn test_diff_cipher_and_plain() {
         let mut enigma_machine = EnigmaMachine::new_with_all_setting(
@@ -36,6 +129,7 @@ mod test {
     }
     
     #[test]
+    #[allow(clippy::needless_return)]
     fn test_plugboard() {
         let mut enigma_machine = EnigmaMachine::new_with_all_setting(
             vec![
@@ -105,4 +199,1864 @@ mod test {
         // fail test intentionally to see output cipher whether the char is not a repeating pattern.
         // assert!(false);
     }
+
+    #[test]
+    fn test_message_procedure() {
+        // 1941 "barbarossa" message, part one. wheel order II IV V,
+        // rings 02 21 12 (BUL), reflector B, read left to right.
+        // the crate take rotors, rings and windows fast rotor first.
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::V,
+                EnigmaRotorSetting::IV,
+                EnigmaRotorSetting::II,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        enigma_machine.set_ring("LUB");
+        for wire in ["AV", "BS", "CG", "DL", "FU", "HZ", "IN", "KM", "OW", "RX"] {
+            let wire = wire.as_bytes();
+            let wire = EnigmaPlugBoardWire(wire[0] as char, wire[1] as char);
+            let _ = enigma_machine.add_plug_wire(wire);
+        }
+
+        let sent = "1840 = 2TLE = 1TL = 179 = WXC KCH =
+RFUGZ EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY
+SIOZV EQMIK UBPMM YLKLT TDEIS MDICA GYKUA CTCDO MOHWX MUUIA
+UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA YUPAD TXQSP
+INQMA TLPIF SVKDA SCTAC DPBOP VHJK";
+        let plain_text = "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAXNORDWESTLXSEBEZXSEBEZXUAFFLIEGERSTRASZERIQTUNGXDUBROWKIXDUBROWKIXOPOTSCHKAXOPOTSCHKAXUMXEINSAQTDREINULLXUHRANGETRETENXANGRIFFXINFXRGTX";
+
+        let received = EnigmaMessage::parse(sent).unwrap();
+        assert_eq!(received.header.time, "1840");
+        assert_eq!(received.header.part, Some(EnigmaMessagePart { number: 1, total: 2 }));
+        assert_eq!(received.header.grundstellung, "CXW");
+        assert_eq!(received.kenngruppe, "RFUGZ");
+        assert_eq!(received.decode_message_key(&mut enigma_machine).unwrap(), "ALB");
+        assert_eq!(received.decode(&mut enigma_machine).unwrap(), plain_text);
+
+        // the same key on the sending side give the same message back
+        let mut message = EnigmaMessage::encode(
+            &mut enigma_machine,
+            "1840",
+            "CXW",
+            "ALB",
+            "RFUGZ",
+            plain_text,
+        )
+        .unwrap();
+        message.header.part = received.header.part;
+        assert_eq!(message, received);
+        assert_eq!(EnigmaMessage::parse(&message.to_string()).unwrap(), received);
+
+        let header = EnigmaMessageHeader::parse("1220 = 204 = QSZ VUB =").unwrap();
+        assert_eq!(header.time, "1220");
+        assert_eq!(header.letter_count, 204);
+        assert_eq!(header.grundstellung, "ZSQ");
+        assert_eq!(header.encoded_message_key, "VUB");
+        assert_eq!(header.to_string(), "1220 = 204 = QSZ VUB =");
+
+        // a group lost on the way is caught by the letter count
+        let truncated = &sent[..sent.len() - 5];
+        assert!(matches!(
+            EnigmaMessage::parse(truncated),
+            Err(EnigmaMessageError::LetterCountMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_naval_procedure() {
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::III,
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::IV,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+
+        // only the pairs this message needs, a real table cover all 676
+        let table = EnigmaBigramTable::new_with_pairs(&[
+            ("XR", "UA"),
+            ("SA", "PV"),
+            ("WF", "LZ"),
+            ("QY", "KK"),
+        ])
+        .unwrap();
+        assert_eq!(table.substitute("UA").unwrap(), "XR");
+        assert!(table.substitute("AA").is_none());
+        assert!(!table.is_complete());
+        assert!(matches!(
+            EnigmaBigramTable::new_with_pairs(&[("AB", "CD"), ("CD", "EF")]),
+            Err(EnigmaBigramTableError::AlreadyHavePair(..))
+        ));
+
+        let plain_text = "WETTERVORHERSAGEBISKAYA";
+        let message = EnigmaNavalMessage::encode(
+            &mut enigma_machine,
+            &table,
+            "JKM",
+            "SWQ",
+            "RAF",
+            ('X', 'Y'),
+            plain_text,
+        )
+        .unwrap();
+        assert_eq!(message.indicator, ["UPLK".to_owned(), "AVZK".to_owned()]);
+
+        let sent = message.to_string();
+        assert!(sent.starts_with("UPLK AVZK "));
+        assert!(sent.ends_with(" UPLK AVZK"));
+
+        let received = EnigmaNavalMessage::parse(sent.as_str()).unwrap();
+        assert_eq!(received, message);
+        assert_eq!(
+            received.recover_trigrams(&table).unwrap(),
+            ("SWQ".to_owned(), "RAF".to_owned())
+        );
+        assert_eq!(
+            received.decode(&mut enigma_machine, &table, "JKM").unwrap(),
+            plain_text
+        );
+
+        // on the M4 the trigram set the three rotors by the entry, the greek
+        // wheel stay at the grundstellung. Beta at 'A' with the thin reflector B
+        // is the three rotor machine with reflector B
+        let mut m4_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::III,
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::IV,
+                EnigmaRotorSetting::BETA,
+            ],
+            "ENKQAUYWJICOPBLMDXZVFTHRGS",
+        );
+        let m4_message = EnigmaNavalMessage::encode(
+            &mut m4_machine,
+            &table,
+            "JKMA",
+            "SWQ",
+            "RAF",
+            ('X', 'Y'),
+            plain_text,
+        )
+        .unwrap();
+        assert_eq!(m4_message, message);
+        assert_eq!(
+            m4_message.decode(&mut m4_machine, &table, "JKMA").unwrap(),
+            plain_text
+        );
+        assert_eq!(
+            m4_message.decode(&mut m4_machine, &table, "JKM"),
+            Err(EnigmaMessageError::InvalidKey)
+        );
+
+        // wrong repeat at the end
+        let garbled = sent.replace(" UPLK AVZK", " UPLK AVZA");
+        assert_eq!(
+            EnigmaNavalMessage::parse(garbled.as_str()),
+            Err(EnigmaMessageError::IndicatorMismatch)
+        );
+    }
+
+    #[test]
+    fn test_group_format_and_parts() {
+        let format = EnigmaGroupFormat {
+            group_size: 5,
+            groups_per_line: 2,
+        };
+        assert_eq!(format.format("ABCDEFGHIJKLMN"), "ABCDE FGHIJ\nKLMN");
+        assert_eq!(
+            EnigmaGroupFormat::KRIEGSMARINE.format("ABCDEFGHIJ"),
+            "ABCD EFGH IJ"
+        );
+        assert_eq!(
+            EnigmaGroupFormat::parse(" abcde FGHIJ\n\tKLMN ").unwrap(),
+            "ABCDEFGHIJKLMN"
+        );
+        assert_eq!(
+            EnigmaGroupFormat::parse("ABCDE 12345"),
+            Err(EnigmaMessageError::InvalidText)
+        );
+
+        let header = EnigmaMessageHeader::parse("1840 = 2TLE = 1TL = 179 = WXC KCH =").unwrap();
+        assert_eq!(header.part, Some(EnigmaMessagePart { number: 1, total: 2 }));
+        assert_eq!(header.letter_count, 179);
+        assert_eq!(header.to_string(), "1840 = 2TLE = 1TL = 179 = WXC KCH =");
+
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::III,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        let plain_text = "TTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTT";
+        let mut random = EnigmaRandom::new(1941);
+        let parts = EnigmaMessage::encode_parts(
+            &mut enigma_machine,
+            "1840",
+            "XYNBV",
+            plain_text,
+            30,
+            &mut random,
+        )
+        .unwrap();
+
+        // 25 letters of text per part
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2].header.part, Some(EnigmaMessagePart { number: 3, total: 3 }));
+        assert_eq!(parts[2].header.letter_count, 5 + 23);
+        // every part has its own keys
+        assert_ne!(parts[0].header.grundstellung, parts[1].header.grundstellung);
+        assert_eq!(
+            EnigmaMessage::encode_parts(
+                &mut enigma_machine,
+                "1840",
+                "XYNBV",
+                plain_text,
+                5,
+                &mut random,
+            ),
+            Err(EnigmaMessageError::InvalidPartLength)
+        );
+
+        // send them, receive them out of order
+        let received = parts
+            .iter()
+            .rev()
+            .map(|p| EnigmaMessage::parse(p.format(&format).as_str()).unwrap())
+            .collect::<Vec<EnigmaMessage>>();
+        assert_eq!(
+            EnigmaMessage::decode_parts(&received, &mut enigma_machine).unwrap(),
+            plain_text
+        );
+        assert_eq!(
+            EnigmaMessage::decode_parts(&received[..2], &mut enigma_machine),
+            Err(EnigmaMessageError::MissingPart(1))
+        );
+    }
+
+    #[test]
+    fn test_text_convention() {
+        let heer = EnigmaTextConvention::HEER;
+        let text = "Angriff um 1830 Uhr, Meldung an \"Müller\" nach Kurtinowa.";
+        let typed = heer.encode(text).unwrap();
+        assert_eq!(
+            typed,
+            "ANGRIFFXUMXEINSAQTDREINULLXUHRXZZXMELDUNGXANXJJMUELLERJJXNAQXKURTINOWAX"
+        );
+        assert_eq!(
+            heer.decode(&typed),
+            "ANGRIFF UM 1830 UHR, MELDUNG AN \"MUELLER\" NACH KURTINOWA."
+        );
+
+        // whatever comes out can go through the machine
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::III,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        enigma_machine.set_window("AAA");
+        let cipher_text = enigma_machine.encode_str(&typed);
+        enigma_machine.set_window("AAA");
+        let decode_text = enigma_machine.encode_str(&cipher_text);
+        assert_eq!(heer.decode(&decode_text), heer.decode(&typed));
+
+        let marine = EnigmaTextConvention::KRIEGSMARINE;
+        let typed = marine.encode("Luftdruck 1018 steigend? Wind aus Nordost.").unwrap();
+        assert_eq!(typed, "LUFTDRUCKXYQPQIYXSTEIGENDXUDXWINDXAUSXNORDOSTX");
+        assert_eq!(
+            marine.decode(&typed),
+            "LUFTDRUCK 1018 STEIGEND? WIND AUS NORDOST."
+        );
+
+        assert_eq!(marine.encode("10 20").unwrap(), "YQPYXYWPY");
+        assert_eq!(marine.decode("YQPYXYWPY"), "10 20");
+
+        // CH before U stay as it is, so QU and CHU both come back
+        for word in ["SCHUTZ", "BUCHUNG", "QUELLE", "NACHT", "SCHUHE", "QUARTIERSUCHE"] {
+            assert_eq!(heer.decode(&heer.encode(word).unwrap()), word);
+        }
+        assert_eq!(heer.encode("Schutz").unwrap(), "SCHUTZ");
+        assert_eq!(heer.encode("Nacht").unwrap(), "NAQT");
+
+        // punctuation is only a word of its own, not the end of one
+        assert_eq!(heer.decode(&heer.encode("Anfrage.").unwrap()), "ANFRAGE.");
+        assert_eq!(heer.decode("ANFRAGEXZZXANFRAGEXFRAGE"), "ANFRAGE, ANFRAGE?");
+        let y_space = EnigmaTextConvention {
+            space: "Y",
+            ..EnigmaTextConvention::HEER
+        };
+        let text = "Fax an Boxer, Jazz?";
+        let typed = y_space.encode(text).unwrap();
+        assert_eq!(typed, "FAXYANYBOXERYZZYJAZZYFRAGE");
+        assert_eq!(y_space.decode(&typed), "FAX AN BOXER, JAZZ?");
+
+        assert_eq!(heer.encode("50%"), Err(EnigmaMessageError::InvalidText));
+    }
+
+    #[test]
+    fn test_encode_traced() {
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::III,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        let _ = enigma_machine.add_plug_wire(EnigmaPlugBoardWire('A', 'R'));
+
+        // start just before the first rotor's notch, so the second one steps too
+        let plain_text = "ILOVERUST";
+        enigma_machine.set_window("PAA");
+        let cipher_text = enigma_machine.encode_str(plain_text);
+        enigma_machine.set_window("PAA");
+        let traces = enigma_machine.encode_str_traced(plain_text);
+
+        // trace should agree with the normal encoding
+        assert_eq!(
+            traces.iter().map(|t| t.output).collect::<String>(),
+            cipher_text
+        );
+        assert_eq!(traces[0].window_before, "PAA");
+        assert_eq!(traces[0].stepped, vec![true, false, false]);
+        assert_eq!(traces[1].window_before, "QAA");
+        assert_eq!(traces[1].window_after, "RBA");
+        assert_eq!(traces[1].stepped, vec![true, true, false]);
+        assert_eq!(enigma_machine.get_window(), "YBA");
+
+        // A is wired to R in the plugboard
+        let trace = &traces[7];
+        assert_eq!(trace.input, 'S');
+        assert_eq!(trace.rotors_forward.len(), 3);
+        assert_eq!(trace.rotors_backward.len(), 3);
+        assert_eq!(traces[5].input, 'R');
+        assert_eq!(traces[5].plugboard_in, 'A');
+        // reflector never map a letter to itself
+        traces
+            .iter()
+            .for_each(|t| assert_ne!(t.rotors_forward[2], t.reflector));
+
+        // I go in with the first rotor at Q, straight through the plugboard
+        let trace = &traces[0];
+        assert_eq!(trace.plugboard_in, 'I');
+        assert_eq!(trace.rotors_forward, vec!['M', 'W', 'U']);
+        assert_eq!(trace.reflector, 'C');
+        assert_eq!(trace.rotors_backward, vec!['G', 'R', 'Z']);
+        assert_eq!(trace.output, 'Z');
+        assert_eq!(trace.to_string(), "PAA -> QAA  I > I > MWU > C > GRZ > Z");
+    }
+
+    #[test]
+    fn test_bombe() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+
+        let mut enigma_machine =
+            EnigmaMachine::new_with_all_setting(vec![rotors[1], rotors[4], rotors[2]], reflector);
+        let wires = [
+            ('A', 'M'),
+            ('F', 'I'),
+            ('N', 'V'),
+            ('P', 'S'),
+            ('T', 'U'),
+            ('W', 'Z'),
+        ];
+        wires.iter().for_each(|&(a, b)| {
+            let _ = enigma_machine.add_plug_wire(EnigmaPlugBoardWire(a, b));
+        });
+        enigma_machine.set_window("KDR");
+        let cipher_text = enigma_machine.encode_str("WETTERVORHERSAGEXNORDSEEXWINDAUSWESTSUEDWEST");
+
+        let crib = "WETTERVORHERSAGE";
+        let bombe = EnigmaBombe::new(&rotors, reflector, &cipher_text, crib, 0).unwrap();
+        let stops = bombe.run_wheel_order(&[1, 4, 2]);
+
+        let stop = stops.iter().find(|stop| stop.window == "KDR").unwrap();
+        // every stecker the stop find must be right
+        stop.steckers.iter().for_each(|wire| {
+            assert!(wires.contains(&(wire.0, wire.1)));
+        });
+        assert!(stop
+            .self_steckered
+            .iter()
+            .all(|c| !wires.iter().any(|&(a, b)| a == *c || b == *c)));
+
+        // crib under the wrong place, a letter would encode to itself
+        let clash = (0..cipher_text.len() - crib.len())
+            .find(|&offset| {
+                crib.bytes()
+                    .zip(cipher_text[offset..].bytes())
+                    .any(|(p, c)| p == c)
+            })
+            .unwrap();
+        assert!(matches!(
+            EnigmaBombe::new(&rotors, reflector, &cipher_text, crib, clash),
+            Err(EnigmaBombeError::CribClash(_))
+        ));
+    }
+
+    #[test]
+    fn test_bombe_wheel_orders() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+
+        let mut enigma_machine =
+            EnigmaMachine::new_with_all_setting(vec![rotors[2], rotors[0], rotors[1]], reflector);
+        let wires = [('B', 'Q'), ('C', 'R'), ('D', 'I'), ('E', 'J'), ('K', 'W')];
+        wires.iter().for_each(|&(a, b)| {
+            let _ = enigma_machine.add_plug_wire(EnigmaPlugBoardWire(a, b));
+        });
+        enigma_machine.set_window("MFT");
+        let cipher_text = enigma_machine.encode_str("KEINEBESONDERENEREIGNISSEXWETTERKLAR");
+
+        // every wheel order of the three rotors
+        let crib = "KEINEBESONDERENEREIGNISSE";
+        let bombe = EnigmaBombe::new(&rotors, reflector, &cipher_text, crib, 0).unwrap();
+        let stops = bombe.run(3);
+        let stop = stops
+            .iter()
+            .find(|stop| stop.rotor_order == vec![2, 0, 1] && stop.window == "MFT")
+            .unwrap();
+        stop.steckers.iter().for_each(|wire| {
+            assert!(wires.contains(&(wire.0, wire.1)));
+        });
+        assert!(!stop.steckers.is_empty());
+        // a long crib leave few false stops over all the wheel orders
+        assert!(stops.len() < 20);
+    }
+
+    #[test]
+    fn test_menu() {
+        //          WETTERVORHERSAGE
+        let menu = EnigmaMenu::new("QWERTZUIOPASDFGHJKLYXCVBNM", "WETTERVORHERSAGE", 3).unwrap();
+        assert_eq!(menu.links().len(), 16);
+        assert_eq!(
+            menu.links()[0],
+            EnigmaMenuLink {
+                crib: 22,
+                cipher: 17,
+                position: 3
+            }
+        );
+
+        // W-R, E-T, T-Z, T-U, E-I, R-O, V-P, O-A, R-S, H-D, E-F, R-G, S-H, A-J, G-K, E-L
+        // three parts, around R, around E, and V-P, no loop at all
+        let components = menu.components();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0].links, 9);
+        assert_eq!(components[0].letters.len(), 10);
+        assert_eq!(components[1].links, 6);
+        assert_eq!(menu.closures(), 0);
+        assert_eq!(menu.test_letter(), 17);
+        assert_eq!(menu.expected_stops(3), 26_f64.powi(4));
+
+        let dot = menu.to_dot();
+        assert!(dot.starts_with("graph menu {\n    R [shape=doublecircle];\n"));
+        assert!(dot.contains("    W -- R [label=\"3\"];\n"));
+        assert!(dot.ends_with("}\n"));
+
+        // a loop: A-B, B-C, C-A
+        let menu = EnigmaMenu::new("BCA", "ABC", 0).unwrap();
+        assert_eq!(menu.closures(), 1);
+        assert_eq!(menu.components().len(), 1);
+        assert_eq!(menu.expected_stops(3), 26_f64.powi(3));
+
+        // best offset should have no clash and at least as many closures as the rest
+        let cipher_text = "XJQWERTZUIOPASDFGHJKLYXCVBNMWETTERQW";
+        let ranked = EnigmaMenu::rank_offsets(cipher_text, "WETTER");
+        assert!(!ranked.is_empty());
+        assert!(ranked
+            .iter()
+            .all(|(_, m)| m.main_component().closures <= ranked[0].1.main_component().closures));
+        assert!(ranked.iter().all(|(offset, _)| *offset != 28));
+    }
+
+    #[test]
+    fn test_crib_drag() {
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::III,
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::II,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        enigma_machine.set_window("XKC");
+        let plain_text = "ANXOBERKOMMANDOXWETTERVORHERSAGEXREGENXHEILHITLER";
+        let cipher_text = enigma_machine.encode_str(plain_text);
+
+        let offsets = EnigmaCribPlacement::drag(&cipher_text, "WETTERVORHERSAGE");
+        assert!(offsets.contains(&16));
+        assert!(offsets.len() < cipher_text.len() - 16);
+        // every offset left really has no clash
+        offsets.iter().for_each(|&offset| {
+            assert!("WETTERVORHERSAGE"
+                .bytes()
+                .zip(cipher_text[offset..].bytes())
+                .all(|(p, c)| p != c));
+        });
+
+        let ranked = EnigmaCribPlacement::rank(&cipher_text, &EnigmaCribDictionary::common());
+        assert!(ranked.contains(&EnigmaCribPlacement {
+            crib: "WETTERVORHERSAGE".to_owned(),
+            offset: 16,
+            dictionary: 0,
+        }));
+        assert!(ranked
+            .windows(2)
+            .all(|w| w[0].dictionary <= w[1].dictionary));
+
+        let combined =
+            EnigmaCribPlacement::combine(&cipher_text, &["WETTERVORHERSAGE", "HEILHITLER"]);
+        assert!(combined
+            .iter()
+            .any(|placed| placed[0].offset == 16 && placed[1].offset == 39));
+        // overlapping cribs must agree
+        combined.iter().for_each(|placed| {
+            let (a, b) = (&placed[0], &placed[1]);
+            (0..cipher_text.len()).for_each(|idx| {
+                if let (Some(x), Some(y)) = (a.plain_text_at(idx), b.plain_text_at(idx)) {
+                    assert_eq!(x, y);
+                }
+            });
+        });
+    }
+
+    #[test]
+    fn test_ioc_search() {
+        assert!(index_of_coincidence(GERMAN_TEXT) > 0.07);
+        assert_eq!(index_of_coincidence("AB"), 0.0);
+        assert_eq!(index_of_coincidence("AA"), 1.0);
+
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let mut enigma_machine =
+            EnigmaMachine::new_with_all_setting(vec![rotors[3], rotors[0], rotors[2]], reflector);
+        enigma_machine.set_window("HQN");
+        let cipher_text = enigma_machine.encode_str(&GERMAN_TEXT[..250]);
+        assert!(index_of_coincidence(&cipher_text) < 0.05);
+
+        // one wrong wheel order and the right one
+        let search = EnigmaIocSearch::new(&rotors, reflector, 5);
+        let candidates = search.run_wheel_orders(&cipher_text, &[vec![0, 1, 2], vec![3, 0, 2]]);
+        assert_eq!(candidates.len(), 5);
+        assert_eq!(candidates[0].rotor_order, vec![3, 0, 2]);
+        assert_eq!(candidates[0].window, "HQN");
+        assert!(candidates.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+
+    #[test]
+    fn test_plugboard_climb() {
+        let rotors = [
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::V,
+            EnigmaRotorSetting::I,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(rotors.to_vec(), reflector);
+        let wires = [
+            EnigmaPlugBoardWire('A', 'K'),
+            EnigmaPlugBoardWire('B', 'Y'),
+            EnigmaPlugBoardWire('C', 'P'),
+            EnigmaPlugBoardWire('E', 'W'),
+            EnigmaPlugBoardWire('G', 'N'),
+            EnigmaPlugBoardWire('H', 'O'),
+            EnigmaPlugBoardWire('L', 'T'),
+            EnigmaPlugBoardWire('R', 'U'),
+        ];
+        wires.iter().for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+        assert_eq!(enigma_machine.get_plug_wires(), wires.to_vec());
+        enigma_machine.set_window("DMX");
+        let cipher_text = enigma_machine.encode_str(WEATHER_TEXT);
+
+        // trained on some other text
+        let bigram = EnigmaNgramFitness::train(2, GERMAN_TEXT).unwrap();
+        assert!(bigram.score(WEATHER_TEXT) > bigram.score(&cipher_text));
+
+        let climb = EnigmaPlugBoardClimb::new(&rotors, reflector, "DMX");
+        let mut random = EnigmaRandom::new(35);
+        let result = climb
+            .run(&cipher_text, &[&EnigmaIocFitness, &bigram], &mut random)
+            .unwrap();
+        assert_eq!(result.wires, wires.to_vec());
+
+        enigma_machine.clear_plug_wires();
+        assert!(enigma_machine.get_plug_wires().is_empty());
+        result.wires.iter().for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+        enigma_machine.set_window("DMX");
+        assert_eq!(enigma_machine.encode_str(&cipher_text), WEATHER_TEXT);
+        assert_eq!(
+            enigma_machine.remove_plug_wire('K'),
+            Some(EnigmaPlugBoardWire('K', 'A'))
+        );
+        assert_eq!(enigma_machine.remove_plug_wire('K'), None);
+    }
+
+    #[test]
+    fn test_ring_search() {
+        let rotors = [
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::V,
+            EnigmaRotorSetting::I,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(rotors.to_vec(), reflector);
+        let wires = [
+            EnigmaPlugBoardWire('A', 'K'),
+            EnigmaPlugBoardWire('C', 'P'),
+            EnigmaPlugBoardWire('H', 'O'),
+            EnigmaPlugBoardWire('R', 'U'),
+        ];
+        wires.iter().for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+        enigma_machine.set_ring("FKC");
+        assert_eq!(enigma_machine.get_ring(), "FKC");
+        enigma_machine.set_window("QXR");
+        let cipher_text = enigma_machine.encode_str(WEATHER_TEXT);
+
+        // what a search with every ring at 'A' would find
+        let search = EnigmaRingSearch::new(&rotors, reflector, "LNP", &wires, 5);
+        let bigram = EnigmaNgramFitness::train(2, GERMAN_TEXT).unwrap();
+        let candidates = search.run(&cipher_text, &bigram).unwrap();
+        assert_eq!(candidates.len(), 5);
+        assert_eq!(candidates[0].ring, "FKA");
+        assert_eq!(candidates[0].window, "QXP");
+        assert!(candidates[0].score > candidates[1].score);
+
+        enigma_machine.set_ring(&candidates[0].ring);
+        enigma_machine.set_window(&candidates[0].window);
+        assert_eq!(enigma_machine.encode_str(&cipher_text), WEATHER_TEXT);
+
+        let search = EnigmaRingSearch::new(&rotors, reflector, "lnp", &wires, 5);
+        assert_eq!(
+            search.run(&cipher_text, &bigram),
+            Err(EnigmaRingSearchError::InvalidWindow)
+        );
+        let search = EnigmaRingSearch::new(&rotors, reflector, "LN", &wires, 5);
+        assert_eq!(
+            search.run(&cipher_text, &bigram),
+            Err(EnigmaRingSearchError::InvalidWindow)
+        );
+        let clash = [wires[0], EnigmaPlugBoardWire('A', 'B')];
+        let search = EnigmaRingSearch::new(&rotors, reflector, "LNP", &clash, 5);
+        assert_eq!(
+            search.run(&cipher_text, &bigram),
+            Err(EnigmaRingSearchError::InvalidWire(clash[1]))
+        );
+    }
+
+    #[test]
+    fn test_language_stats() {
+        let frequencies = letter_frequencies(GERMAN_TEXT);
+        assert!((frequencies.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+        assert!(frequencies[4] > frequencies[23]);
+
+        // enigma text use X for space, so compare on plain prose
+        let german_text = "DERWETTERBERICHTFUERDIENACHTMELDETIMNORDENSTARKEBEWOELKUNG";
+        let english_text = "THEWEATHERREPORTFORTONIGHTGIVESHEAVYCLOUDANDSCATTEREDSHOWERS";
+        let german = EnigmaLanguage::German.letter_frequencies();
+        let english = EnigmaLanguage::English.letter_frequencies();
+        assert!(chi_squared(german_text, &german) < chi_squared(german_text, &english));
+        assert!(chi_squared(english_text, &english) < chi_squared(english_text, &german));
+
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::III,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        let cipher_text = enigma_machine.encode_str(GERMAN_TEXT);
+
+        let chi = EnigmaChiSquaredFitness::new_with_language(EnigmaLanguage::German);
+        assert!(chi.score(GERMAN_TEXT) > chi.score(&cipher_text));
+        let bigram = EnigmaNgramFitness::bigram(EnigmaLanguage::German);
+        assert_eq!(bigram.n(), 2);
+        assert!(bigram.score(GERMAN_TEXT) > bigram.score(&cipher_text));
+        let english_bigram = EnigmaNgramFitness::bigram(EnigmaLanguage::English);
+        assert!(english_bigram.score(english_text) > bigram.score(english_text));
+        // built once and shared
+        assert!(std::ptr::eq(
+            bigram,
+            EnigmaNgramFitness::bigram(EnigmaLanguage::German)
+        ));
+        let trigram = EnigmaNgramFitness::trigram(EnigmaLanguage::German);
+        let quadgram = EnigmaNgramFitness::quadgram(EnigmaLanguage::German);
+        assert_eq!((trigram.n(), quadgram.n()), (3, 4));
+        assert!(trigram.score(german_text) > trigram.score(&cipher_text));
+        assert!(quadgram.score(german_text) > quadgram.score(&cipher_text));
+        let english_quadgram = EnigmaNgramFitness::quadgram(EnigmaLanguage::English);
+        assert!(english_quadgram.score(english_text) > quadgram.score(english_text));
+        assert!(matches!(
+            EnigmaNgramFitness::train(0, GERMAN_TEXT),
+            Err(EnigmaNgramError::InvalidLength(0))
+        ));
+        assert!(matches!(
+            EnigmaNgramFitness::train(5, GERMAN_TEXT),
+            Err(EnigmaNgramError::InvalidLength(5))
+        ));
+
+        // nothing to count
+        assert_eq!(chi_squared("", &german), f64::INFINITY);
+        assert_eq!(chi_squared("1234 ?!", &german), f64::INFINITY);
+        assert!(chi.score("") < chi.score(&cipher_text));
+
+        let path = std::env::temp_dir().join("enigma_test_corpus.txt");
+        std::fs::write(&path, EnigmaLanguage::English.corpus()).unwrap();
+        let trained = EnigmaNgramFitness::train_from_file(2, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let built_in = EnigmaNgramFitness::bigram(EnigmaLanguage::English);
+        assert_eq!(trained.score(english_text), built_in.score(english_text));
+        assert!(matches!(
+            EnigmaNgramFitness::train_from_file(2, &path),
+            Err(EnigmaNgramError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_cyclometer() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let mut enigma_machine =
+            EnigmaMachine::new_with_all_setting(vec![rotors[2], rotors[0], rotors[1]], reflector);
+        [
+            EnigmaPlugBoardWire('A', 'Q'),
+            EnigmaPlugBoardWire('B', 'J'),
+            EnigmaPlugBoardWire('D', 'W'),
+            EnigmaPlugBoardWire('F', 'X'),
+            EnigmaPlugBoardWire('K', 'S'),
+            EnigmaPlugBoardWire('N', 'V'),
+        ]
+        .iter()
+        .for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+
+        // message keys of the day, each typed twice at the ground setting
+        let mut random = EnigmaRandom::new(38);
+        let indicators = (0..120)
+            .map(|_| {
+                let key = random.next_letters(3);
+                enigma_machine.set_window("MBT");
+                enigma_machine.encode_str(&format!("{}{}", key, key))
+            })
+            .collect::<Vec<String>>();
+        let indicators = indicators.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+
+        assert_eq!(
+            EnigmaCycleCharacteristic::from_indicators(&indicators[..3]),
+            Err(EnigmaCyclometerError::Incomplete)
+        );
+        assert_eq!(
+            EnigmaCycleCharacteristic::from_indicators(&["ABCDE"]),
+            Err(EnigmaCyclometerError::InvalidIndicator)
+        );
+        let observed = EnigmaCycleCharacteristic::from_indicators(&indicators).unwrap();
+        for lengths in [&observed.ad, &observed.be, &observed.cf] {
+            assert_eq!(lengths.iter().sum::<usize>(), 26);
+            // cycles of the same length always come in pairs
+            lengths
+                .chunks(2)
+                .for_each(|pair| assert_eq!(pair[0], pair[1]));
+        }
+
+        let cyclometer = EnigmaCyclometer::new(&rotors, reflector);
+        assert_eq!(cyclometer.characteristic(&[2, 0, 1], "MBT"), observed);
+
+        let catalog = cyclometer.catalog_wheel_orders(&[vec![2, 0, 1], vec![0, 2, 1]]);
+        assert_eq!(catalog.setting_count(), 2 * 17576);
+        assert!(catalog.characteristic_count() > 1000);
+        let found = catalog.lookup(&observed);
+        assert!(found.len() * 100 < catalog.setting_count());
+        assert!(found.contains(&EnigmaCatalogEntry {
+            rotor_order: vec![2, 0, 1],
+            window: "MBT".to_owned(),
+        }));
+    }
+
+    #[test]
+    fn test_zygalski_sheets() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let mut enigma_machine =
+            EnigmaMachine::new_with_all_setting(vec![rotors[1], rotors[2], rotors[0]], reflector);
+        [
+            EnigmaPlugBoardWire('C', 'O'),
+            EnigmaPlugBoardWire('D', 'I'),
+            EnigmaPlugBoardWire('F', 'R'),
+            EnigmaPlugBoardWire('H', 'U'),
+            EnigmaPlugBoardWire('J', 'W'),
+            EnigmaPlugBoardWire('L', 'S'),
+        ]
+        .iter()
+        .for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+        enigma_machine.set_ring("AGM");
+
+        // every operator pick a ground setting and a message key
+        let mut random = EnigmaRandom::new(39);
+        let mut females = vec![];
+        for _ in 0..300 {
+            let ground = random.next_letters(3);
+            let key = random.next_letters(3);
+            enigma_machine.set_window(&ground);
+            let indicator = enigma_machine.encode_str(&format!("{}{}", key, key));
+            females.extend(EnigmaFemale::from_indicator(&ground, &indicator));
+        }
+        assert!(females.len() > 20);
+        assert_eq!(
+            EnigmaFemale::from_indicator("ABC", "QWEQRT"),
+            vec![EnigmaFemale {
+                ground: "ABC".to_owned(),
+                pair: 0,
+            }]
+        );
+
+        // a few females are lost to the middle rotor turning over in the
+        // indicator, which the sheets don't know about
+        let mut sheets = EnigmaZygalskiSheets::new(&rotors, reflector);
+        sheets.tolerance = 4;
+        let sheet = sheets.sheets(&[1, 2, 0]);
+        assert_eq!(sheet.len(), 26);
+        assert_eq!(sheet[7].left, "H");
+        assert_eq!(sheet[7].to_string().lines().count(), 27);
+        let female = &females[0];
+        let core = female
+            .ground
+            .bytes()
+            .zip("AGM".bytes())
+            .map(|(g, r)| ((g + 26 - r) % 26 + b'A') as char)
+            .collect::<Vec<char>>();
+        assert!(sheet[(core[2] as u8 - b'A') as usize].is_marked(core[1], core[0], female.pair));
+
+        // ground setting as read from a header is written left to right,
+        // the female takes it fast rotor first
+        let written = female.ground.chars().rev().collect::<String>();
+        let header =
+            EnigmaMessageHeader::parse(&format!("1220 = 204 = {} VUB =", written)).unwrap();
+        assert_eq!(header.grundstellung, female.ground);
+        let from_header = (0..1000)
+            .find_map(|_| {
+                let key = random.next_letters(3);
+                enigma_machine.set_window(&header.grundstellung);
+                let indicator = enigma_machine.encode_str(&format!("{}{}", key, key));
+                EnigmaFemale::from_indicator(&header.grundstellung, &indicator)
+                    .into_iter()
+                    .find(|f| f.pair == female.pair)
+            })
+            .unwrap();
+        assert_eq!(&from_header, female);
+
+        let stops = sheets.search_wheel_orders(&females, &[vec![1, 2, 0], vec![0, 1, 2]]);
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0].rotor_order, vec![1, 2, 0]);
+        assert_eq!(stops[0].ring, "AGM");
+        assert!(stops[0].matches + 4 >= females.len());
+    }
+
+    #[test]
+    fn test_rodding() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+
+        // commercial machine, keyboard wired to the entry wheel and no plugboard
+        let mut enigma_machine =
+            EnigmaMachine::new_with_all_setting(vec![rotors[2], rotors[0], rotors[1]], reflector);
+        enigma_machine.set_entry_wheel(EnigmaEntryWheel::QWERTZU);
+        enigma_machine.set_window("RMF");
+        let cipher_text = enigma_machine.encode_str(WEATHER_TEXT);
+        enigma_machine.set_window("RMF");
+        assert_eq!(enigma_machine.encode_str(&cipher_text), WEATHER_TEXT);
+        let mut military =
+            EnigmaMachine::new_with_all_setting(vec![rotors[2], rotors[0], rotors[1]], reflector);
+        military.set_window("RMF");
+        assert_ne!(military.encode_str(WEATHER_TEXT), cipher_text);
+
+        let rodding = EnigmaRodding::new(&rotors, EnigmaEntryWheel::ABC);
+        let square = rodding.rod_square(0);
+        assert_eq!(square.rod('A', 'A'), 'E');
+        assert_eq!(square.rod('A', 'B'), 'K');
+        assert_eq!(square.to_string().lines().count(), 26);
+
+        // crib cover a turnover of the middle rotor (III turn over at V)
+        let rodding = EnigmaRodding::new(&rotors, EnigmaEntryWheel::QWERTZU);
+        let stops = rodding.run(&cipher_text, &WEATHER_TEXT[0..16], 0).unwrap();
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0].rotor, 2);
+        assert_eq!(stops[0].window, 'R');
+        assert_eq!(stops[0].pairs.len(), 2);
+        assert_eq!(
+            rodding.run(&cipher_text, "WETTER", 400),
+            Err(EnigmaRoddingError::CribOutOfRange)
+        );
+        assert_eq!(
+            rodding.run(&cipher_text, "wetter", 0),
+            Err(EnigmaRoddingError::InvalidText)
+        );
+
+        // same message sent again later in the day, only the fast rotor
+        // start where it was, no crib needed to find it
+        enigma_machine.set_window("RDX");
+        let again = enigma_machine.encode_str(WEATHER_TEXT);
+        let stops = rodding.isomorphs(&cipher_text[..40], &again[..40]).unwrap();
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0].rotor, 2);
+        assert_eq!(stops[0].window, 'R');
+        // the middle rotor turn over twice in 40 letters
+        assert_eq!(stops[0].links.len(), 3);
+        assert!(rodding
+            .isomorphs(&cipher_text[..40], &again[1..41])
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            rodding.isomorphs(&cipher_text[..40], &again[..30]),
+            Err(EnigmaRoddingError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn test_banburismus() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let mut enigma_machine =
+            EnigmaMachine::new_with_all_setting(vec![rotors[2], rotors[0], rotors[3]], reflector);
+        [
+            EnigmaPlugBoardWire('B', 'Q'),
+            EnigmaPlugBoardWire('C', 'R'),
+            EnigmaPlugBoardWire('D', 'I'),
+            EnigmaPlugBoardWire('E', 'J'),
+        ]
+        .iter()
+        .for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+
+        // a day of naval traffic, all indicators enciphered at one grundstellung.
+        // keys with the same left rotor and the middle one a click or two apart
+        let plain_text = [EnigmaLanguage::German, EnigmaLanguage::English]
+            .iter()
+            .map(|language| language.corpus())
+            .collect::<String>()
+            .to_uppercase()
+            .chars()
+            .filter(|c| c.is_ascii_uppercase())
+            .collect::<String>();
+        let mut random = EnigmaRandom::new(41);
+        let mut keys = vec![];
+        let messages = (0..plain_text.len() - 250)
+            .step_by(100)
+            .map(|start| {
+                let chunk = &plain_text[start..start + 250];
+                let key = format!(
+                    "C{}{}",
+                    ["N", "O", "P"][random.next_index(3)],
+                    random.next_letters(1)
+                );
+                enigma_machine.set_window("XDK");
+                let indicator = enigma_machine.encode_str(&key);
+                enigma_machine.set_window(&written_to_window(&key));
+                keys.push(key);
+                EnigmaBanburismusMessage {
+                    cipher_text: enigma_machine.encode_str(chunk),
+                    indicator,
+                }
+            })
+            .collect::<Vec<EnigmaBanburismusMessage>>();
+        assert!(messages.len() > 20);
+        // window letter of each indicator letter, for the fast and middle rotor
+        let window_of = |place: usize, letter: char| {
+            messages
+                .iter()
+                .zip(keys.iter())
+                .find(|(m, _)| m.indicator.as_bytes()[place] as char == letter)
+                .map(|(_, key)| key.as_bytes()[place] as char)
+                .unwrap()
+        };
+
+        let banburismus = EnigmaBanburismus::new(&rotors);
+        let hypotheses = banburismus.run(&messages).unwrap();
+        // the notch of every other rotor get in the way of some depth
+        assert!(hypotheses.iter().all(|h| h.rotor_order[0] == 2));
+        let best = &hypotheses[0];
+        assert!(best.fast_key.len() > 5);
+        best.fast_key
+            .iter()
+            .for_each(|&(letter, window)| assert_eq!(window_of(2, letter), window));
+        // three middle letters are too few to pin their windows,
+        // the right ones are among those left
+        let right = hypotheses
+            .iter()
+            .filter(|h| h.rotor_order == vec![2, 0] && h.fast_key == best.fast_key)
+            .collect::<Vec<&EnigmaBanburismusHypothesis>>();
+        assert!(right.iter().all(|h| h.decibans == best.decibans));
+        assert!(right.iter().any(|h| {
+            h.middle_key
+                .iter()
+                .all(|&(letter, window)| window_of(1, letter) == window)
+        }));
+        // a depth found between two indicators also come out on top when sliding by hand
+        let depth = &best.depths[0];
+        let slide = banburismus.slide(&messages, depth.first, depth.second);
+        assert_eq!(slide[0].offset, depth.offset);
+
+        let mut bad = messages.clone();
+        bad[0].indicator = "A1C".to_owned();
+        assert_eq!(banburismus.run(&bad), Err(EnigmaMessageError::InvalidKey));
+    }
+
+    #[test]
+    fn test_depth_search() {
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::V,
+                EnigmaRotorSetting::III,
+                EnigmaRotorSetting::I,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        let plain_text = EnigmaLanguage::German
+            .corpus()
+            .to_uppercase()
+            .chars()
+            .filter(|c| c.is_ascii_uppercase())
+            .collect::<String>();
+        assert!(plain_text.len() > 1800);
+
+        // second message reuse the key of the first, 37 letters in
+        let mut corpus = vec![];
+        enigma_machine.set_window("GLW");
+        corpus.push(enigma_machine.encode_str(&plain_text[0..600]));
+        enigma_machine.set_window("GLW");
+        enigma_machine.encode_str(&plain_text[..37]);
+        corpus.push(enigma_machine.encode_str(&plain_text[600..1200]));
+        enigma_machine.set_window("TBA");
+        corpus.push(enigma_machine.encode_str(&plain_text[1200..1800]));
+        corpus.push("NOT ENIGMA TEXT".to_owned());
+        let corpus = corpus.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+
+        let depths = EnigmaDepthSearch::new().run(&corpus);
+        assert!(!depths.is_empty());
+        let depth = &depths[0];
+        assert_eq!((depth.first, depth.second), (0, 1));
+        assert_eq!((depth.first_start, depth.second_start), (37, 0));
+        assert_eq!(depth.overlap, 563);
+        assert!(depth.rate() > 1.0 / 26.0);
+        assert_eq!(depth.aligned.0, &corpus[0][37..]);
+        assert_eq!(depth.aligned.1, &corpus[1][..563]);
+        assert!(depth.p_value < 1e-6);
+        assert_eq!(depths.len(), 1);
+    }
+
+    #[test]
+    fn test_herivel_and_cillies() {
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::IV,
+                EnigmaRotorSetting::I,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        let _ = enigma_machine.add_plug_wire(EnigmaPlugBoardWire('E', 'Z'));
+        let _ = enigma_machine.add_plug_wire(EnigmaPlugBoardWire('K', 'M'));
+        // rings, ground settings and keys all fast rotor first
+        enigma_machine.set_ring("SKD");
+
+        // first messages of the day, most operators turned the rotors
+        // only a little from the ring setting, one didn't
+        let firsts = [
+            ("TMD", "AAA"),
+            ("VKF", "EWQ"),
+            ("SNG", "FPK"),
+            ("UKE", "TSR"),
+            ("OBX", "ZGH"),
+        ];
+        let mut messages = firsts
+            .iter()
+            .map(|&(ground, key)| {
+                EnigmaMessage::encode(
+                    &mut enigma_machine,
+                    "0710",
+                    ground,
+                    key,
+                    "QWERT",
+                    &WEATHER_TEXT[0..50],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<EnigmaMessage>>();
+
+        let headers = messages
+            .iter()
+            .map(|message| message.header.clone())
+            .collect::<Vec<EnigmaMessageHeader>>();
+        let candidates = EnigmaHerivelTip::new().run(&headers);
+        assert_eq!(candidates.len(), 10);
+        assert_eq!(candidates[0].ring, "SKD");
+        assert_eq!(candidates[0].hits, 4);
+
+        // the ring found goes straight into a machine with the same wheels and plugs
+        let mut day_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::IV,
+                EnigmaRotorSetting::I,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        let _ = day_machine.add_plug_wire(EnigmaPlugBoardWire('E', 'Z'));
+        let _ = day_machine.add_plug_wire(EnigmaPlugBoardWire('K', 'M'));
+        day_machine.set_ring(&candidates[0].ring);
+        assert_eq!(
+            messages[1].decode(&mut day_machine).unwrap(),
+            &WEATHER_TEXT[0..50]
+        );
+
+        // next message start where FPK was left
+        enigma_machine.set_window("FPK");
+        enigma_machine.encode_str(&WEATHER_TEXT[0..50]);
+        let next_key = enigma_machine.get_window();
+        // the middle rotor turned over on the way
+        assert_eq!(next_key, "DQK");
+        messages.push(
+            EnigmaMessage::encode(
+                &mut enigma_machine,
+                "0930",
+                "CJJ",
+                &next_key,
+                "QWERT",
+                &WEATHER_TEXT[50..90],
+            )
+            .unwrap(),
+        );
+        messages.push(
+            EnigmaMessage::encode(
+                &mut enigma_machine,
+                "1015",
+                "WPL",
+                "WPL",
+                "QWERT",
+                &WEATHER_TEXT[90..130],
+            )
+            .unwrap(),
+        );
+
+        let cillies = EnigmaCilly::find(&mut enigma_machine, &messages).unwrap();
+        let found = cillies
+            .iter()
+            .map(|cilly| (cilly.message, cilly.kinds.clone()))
+            .collect::<Vec<(usize, Vec<EnigmaCillyKind>)>>();
+        assert_eq!(
+            found,
+            vec![
+                (0, vec![EnigmaCillyKind::Repeated]),
+                (1, vec![EnigmaCillyKind::Keyboard]),
+                (3, vec![EnigmaCillyKind::Alphabet]),
+                (5, vec![EnigmaCillyKind::PreviousPosition(2)]),
+                (6, vec![EnigmaCillyKind::SameAsGround]),
+            ]
+        );
+        assert_eq!(cillies[3].message_key, next_key);
+        assert_eq!(
+            EnigmaCilly::patterns("YXC"),
+            vec![EnigmaCillyKind::Keyboard]
+        );
+        assert_eq!(
+            EnigmaCillyKind::PreviousPosition(2).to_string(),
+            "end of message 2"
+        );
+    }
+
+    #[test]
+    fn test_wiring_recovery() {
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let wires = [
+            EnigmaPlugBoardWire('A', 'M'),
+            EnigmaPlugBoardWire('F', 'I'),
+            EnigmaPlugBoardWire('N', 'V'),
+            EnigmaPlugBoardWire('P', 'S'),
+            EnigmaPlugBoardWire('T', 'U'),
+        ];
+        // rotor IV play the unknown one
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::IV,
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::I,
+            ],
+            reflector,
+        );
+        wires.iter().for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+        enigma_machine.set_window("UAQ");
+        let plain_text = &GERMAN_TEXT[..200];
+        let cipher_text = enigma_machine.encode_str(plain_text);
+
+        let recovery = EnigmaWiringRecovery::new(
+            &[EnigmaRotorSetting::II, EnigmaRotorSetting::I],
+            reflector,
+            &wires,
+            'J',
+            "UAQ",
+        );
+        let found = recovery.run(plain_text, &cipher_text).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].forward, EnigmaRotorSetting::IV.wiring.forward);
+        assert_eq!(found[0].backward, EnigmaRotorSetting::IV.wiring.backward);
+        assert!(found[0].rotor().is_ok());
+
+        let mut rebuilt = EnigmaMachine::new_with_all_setting(
+            vec![
+                found[0].setting('J'),
+                EnigmaRotorSetting::II,
+                EnigmaRotorSetting::I,
+            ],
+            reflector,
+        );
+        wires.iter().for_each(|&wire| {
+            let _ = rebuilt.add_plug_wire(wire);
+        });
+        rebuilt.set_window("UAQ");
+        assert_eq!(rebuilt.encode_str(&cipher_text), plain_text);
+
+        assert!(matches!(
+            recovery.run(&plain_text[..3], &cipher_text[..3]),
+            Err(EnigmaRecoveryError::Underdetermined(free)) if free.len() >= 20
+        ));
+        assert_eq!(
+            recovery.run(plain_text, &cipher_text[1..]),
+            Err(EnigmaRecoveryError::LengthMismatch)
+        );
+        let rotors = [EnigmaRotorSetting::II, EnigmaRotorSetting::I];
+        let recovery = EnigmaWiringRecovery::new(&rotors, reflector, &wires, 'J', "UA");
+        assert_eq!(
+            recovery.run(plain_text, &cipher_text),
+            Err(EnigmaRecoveryError::InvalidWindow)
+        );
+        let clash = [wires[0], EnigmaPlugBoardWire('M', 'Z')];
+        let recovery = EnigmaWiringRecovery::new(&rotors, reflector, &clash, 'J', "UAQ");
+        assert_eq!(
+            recovery.run(plain_text, &cipher_text),
+            Err(EnigmaRecoveryError::InvalidWire(clash[1]))
+        );
+    }
+
+    #[test]
+    fn test_reflector_recovery() {
+        let reflector = "FVPJIAOYEDRZXWGCTKUQSBNMHL";
+        let rotors = [
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::V,
+            EnigmaRotorSetting::II,
+        ];
+        let wires = [
+            EnigmaPlugBoardWire('B', 'X'),
+            EnigmaPlugBoardWire('G', 'R'),
+            EnigmaPlugBoardWire('K', 'Z'),
+        ];
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(rotors.to_vec(), reflector);
+        wires.iter().for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+        enigma_machine.set_ring("DLK");
+        enigma_machine.set_window("PEW");
+        let plain_text = &WEATHER_TEXT[..120];
+        let cipher_text = enigma_machine.encode_str(plain_text);
+
+        let recovery = EnigmaReflectorRecovery::new(&rotors, &wires, "DLK", "PEW");
+        let found = recovery.run(plain_text, &cipher_text).unwrap();
+        (0..26).for_each(|idx| {
+            assert_eq!(
+                found.encode_index(idx),
+                (reflector.as_bytes()[idx] - b'A') as usize
+            )
+        });
+
+        // one letter of cipher text garbled in transmission
+        let mut garbled = cipher_text.clone().into_bytes();
+        garbled[60] = (b'A'..=b'Z')
+            .find(|&b| b != garbled[60] && b != plain_text.as_bytes()[60])
+            .unwrap();
+        let garbled = String::from_utf8(garbled).unwrap();
+        match recovery.run(plain_text, &garbled) {
+            Err(EnigmaRecoveryError::Conflict(conflicts)) => {
+                assert!(!conflicts.is_empty());
+                assert!(conflicts
+                    .iter()
+                    .all(|conflict| conflict.position == 60 || conflict.earlier == 60));
+            }
+            _ => panic!("garbled letter not reported"),
+        }
+
+        assert!(matches!(
+            recovery.run(&plain_text[..4], &cipher_text[..4]),
+            Err(EnigmaRecoveryError::Underdetermined(_))
+        ));
+
+        // a commercial machine with the keyboard order entry wheel
+        enigma_machine.set_entry_wheel(EnigmaEntryWheel::QWERTZU);
+        enigma_machine.set_window("PEW");
+        let cipher_text = enigma_machine.encode_str(plain_text);
+        let mut recovery = EnigmaReflectorRecovery::new(&rotors, &wires, "DLK", "PEW");
+        assert!(matches!(
+            recovery.run(plain_text, &cipher_text),
+            Err(EnigmaRecoveryError::Conflict(_))
+        ));
+        recovery.set_entry_wheel(EnigmaEntryWheel::QWERTZU);
+        let found = recovery.run(plain_text, &cipher_text).unwrap();
+        (0..26).for_each(|idx| {
+            assert_eq!(
+                found.encode_index(idx),
+                (reflector.as_bytes()[idx] - b'A') as usize
+            )
+        });
+
+        let recovery = EnigmaReflectorRecovery::new(&rotors, &wires, "DLK", "PE");
+        assert!(matches!(
+            recovery.run(plain_text, &cipher_text),
+            Err(EnigmaRecoveryError::InvalidWindow)
+        ));
+        let recovery = EnigmaReflectorRecovery::new(&rotors, &wires, "DL1", "PEW");
+        assert!(matches!(
+            recovery.run(plain_text, &cipher_text),
+            Err(EnigmaRecoveryError::InvalidRing)
+        ));
+    }
+
+    #[test]
+    fn test_start_search() {
+        let mut enigma_machine = EnigmaMachine::new_with_all_setting(
+            vec![
+                EnigmaRotorSetting::V,
+                EnigmaRotorSetting::I,
+                EnigmaRotorSetting::III,
+            ],
+            "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+        );
+        enigma_machine.set_ring("BQE");
+        [
+            EnigmaPlugBoardWire('A', 'T'),
+            EnigmaPlugBoardWire('C', 'L'),
+            EnigmaPlugBoardWire('H', 'W'),
+            EnigmaPlugBoardWire('O', 'Z'),
+        ]
+        .iter()
+        .for_each(|&wire| {
+            let _ = enigma_machine.add_plug_wire(wire);
+        });
+        enigma_machine.set_window("NXF");
+        let cipher_text = enigma_machine.encode_str(&WEATHER_TEXT[..100]);
+        enigma_machine.set_window("AAA");
+
+        let mut search = EnigmaStartSearch::new(&mut enigma_machine);
+        // only part of the crib is known
+        assert_eq!(search.run_crib(&cipher_text, "VORHER??GE", 6), vec!["NXF"]);
+        assert!(search.run_crib(&cipher_text, "VORHER", 98).is_empty());
+
+        let bigram = EnigmaNgramFitness::bigram(EnigmaLanguage::German);
+        let found = search.run_fitness(&cipher_text, &bigram, 3);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].window, "NXF");
+        assert!(found[0].score > found[1].score);
+
+        // machine is left where it was
+        assert_eq!(enigma_machine.get_window(), "AAA");
+    }
+
+    #[test]
+    fn test_annealing() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let key = EnigmaKey::parse("3,0,4 AAA KCW BM DT FQ JO LV").unwrap();
+        assert_eq!(key.to_string(), "3,0,4 AAA KCW BM DT FQ JO LV");
+        assert_eq!(
+            EnigmaKey::parse("3,0,4 AAA KC BM"),
+            Err(EnigmaKeyError::InvalidLetters)
+        );
+        assert_eq!(
+            EnigmaKey::parse("3,0,4 AAA KCW BB"),
+            Err(EnigmaKeyError::InvalidWire)
+        );
+        assert_eq!(
+            EnigmaKey::parse("3;0;4 AAA KCW"),
+            Err(EnigmaKeyError::InvalidRotorOrder)
+        );
+        let mut enigma_machine = EnigmaMachine::new_with_key(&rotors, reflector, &key).unwrap();
+        let cipher_text = enigma_machine.encode_str(WEATHER_TEXT);
+
+        // wheel order and start position came from somewhere else,
+        // the annealing has to find the plugboard
+        let mut annealing = EnigmaAnnealing::new(&rotors, reflector, 3);
+        annealing.rounds = 20;
+        annealing.steps_per_round = 150;
+        annealing.restarts = 0;
+        let bigram = EnigmaNgramFitness::bigram(EnigmaLanguage::German);
+        let start = EnigmaKey {
+            wires: vec![],
+            ..key.clone()
+        };
+        let mut state = annealing.start_with_key(&cipher_text, &bigram, &start, 47);
+        assert!(annealing
+            .run(&cipher_text, &bigram, &mut state, None)
+            .unwrap());
+        // a ring turned with its window give the same decrypt
+        // until a turnover, so only the plugboard is compared
+        let mut wires = state.best.wires.clone();
+        wires.sort_by_key(|w| w.0);
+        assert_eq!(wires, key.wires);
+        let mut found = EnigmaMachine::new_with_key(&rotors, reflector, &state.best).unwrap();
+        assert_eq!(found.encode_str(&cipher_text), WEATHER_TEXT);
+
+        // same run stopped after a few rounds and picked up from the file
+        let path = std::env::temp_dir().join("enigma_test_annealing.txt");
+        annealing.checkpoint = Some(path.clone());
+        let mut stopped = annealing.start_with_key(&cipher_text, &bigram, &start, 47);
+        assert!(!annealing
+            .run(&cipher_text, &bigram, &mut stopped, Some(7))
+            .unwrap());
+        let mut resumed = annealing.resume().unwrap();
+        assert_eq!(resumed, stopped);
+        assert_eq!(resumed.round, 7);
+        assert!(annealing
+            .run(&cipher_text, &bigram, &mut resumed, None)
+            .unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed, state);
+    }
+
+    #[test]
+    fn test_annealing_resume() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let key = EnigmaKey::parse("1,4,2 AAA WXC AD GT LO").unwrap();
+        let mut enigma_machine = EnigmaMachine::new_with_key(&rotors, reflector, &key).unwrap();
+        let cipher_text = enigma_machine.encode_str(WEATHER_TEXT);
+
+        // whole key from a random start, with a restart in the middle
+        let path = std::env::temp_dir().join("enigma_test_annealing_resume.txt");
+        let mut annealing = EnigmaAnnealing::new(&rotors, reflector, 3);
+        annealing.rounds = 6;
+        annealing.steps_per_round = 60;
+        annealing.restarts = 1;
+        annealing.checkpoint_every = 2;
+        annealing.checkpoint = Some(path.clone());
+        let bigram = EnigmaNgramFitness::bigram(EnigmaLanguage::German);
+        let mut state = annealing.start(&cipher_text, &bigram, 8);
+        assert!(annealing
+            .run(&cipher_text, &bigram, &mut state, None)
+            .unwrap());
+
+        // stopped in the second restart and picked up from the saved file
+        let mut stopped = annealing.start(&cipher_text, &bigram, 8);
+        assert!(!annealing
+            .run(&cipher_text, &bigram, &mut stopped, Some(9))
+            .unwrap());
+        let mut resumed = annealing.resume().unwrap();
+        assert_eq!(resumed, stopped);
+        assert_eq!((resumed.restart, resumed.round), (1, 3));
+        assert!(annealing
+            .run(&cipher_text, &bigram, &mut resumed, None)
+            .unwrap());
+        assert_eq!(resumed.best, state.best);
+        assert_eq!(resumed, state);
+
+        // a broken file tell which field is wrong
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            EnigmaAnnealingState::load(&saved.replace("round ", "rounds ")),
+            Err(EnigmaCheckpointError::MissingField("round".to_owned()))
+        );
+        assert_eq!(
+            EnigmaAnnealingState::load(&saved.replacen("temperature ", "temperature x", 1)),
+            Err(EnigmaCheckpointError::InvalidValue("temperature".to_owned()))
+        );
+        assert_eq!(
+            EnigmaAnnealingState::load(&saved.replacen("best ", "best 9,", 1)),
+            Err(EnigmaCheckpointError::InvalidKey(
+                "best".to_owned(),
+                EnigmaKeyError::InvalidLetters
+            ))
+        );
+
+        // a key that read fine but point past the rotors of this search
+        let far = EnigmaKey::parse("9,0,1 AAA AAA").unwrap();
+        assert_eq!(
+            EnigmaMachine::new_with_key(&rotors, reflector, &far).err(),
+            Some(EnigmaKeyError::InvalidRotor(9))
+        );
+        let broken = EnigmaAnnealingState {
+            best: far,
+            ..state.clone()
+        };
+        std::fs::write(&path, broken.save()).unwrap();
+        assert_eq!(
+            annealing.resume(),
+            Err(EnigmaCheckpointError::InvalidKey(
+                "best".to_owned(),
+                EnigmaKeyError::InvalidRotor(9)
+            ))
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            annealing.resume(),
+            Err(EnigmaCheckpointError::Io(std::io::ErrorKind::NotFound))
+        );
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let key = EnigmaKey::parse("1,3,0 AAA QDK AV CX").unwrap();
+        let mut enigma_machine = EnigmaMachine::new_with_key(&rotors, reflector, &key).unwrap();
+        let cipher_text = enigma_machine.encode_str(&WEATHER_TEXT[..150]);
+
+        let mut search = EnigmaParallelSearch::new(&rotors, reflector, 3);
+        search.threads = 4;
+        search.wires = key.wires.clone();
+        let items = search.work_items_for(&[vec![2, 4, 3], vec![1, 3, 0]]);
+        assert_eq!(items.len(), 2 * 26);
+        let bigram = EnigmaNgramFitness::bigram(EnigmaLanguage::German);
+
+        let mut reports = 0;
+        let cancel = AtomicBool::new(false);
+        let found = search.run(
+            &cipher_text,
+            &items,
+            &bigram,
+            &mut |progress| {
+                reports += 1;
+                assert_eq!(progress.done, reports);
+                assert_eq!(progress.total, items.len());
+            },
+            &cancel,
+        );
+        assert_eq!(reports, items.len());
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].key, key);
+
+        // stop as soon as the first item is in
+        let cancel = AtomicBool::new(false);
+        let mut done = 0;
+        search.run(
+            &cipher_text,
+            &items,
+            &bigram,
+            &mut |progress| {
+                done = progress.done;
+                cancel.store(true, Ordering::Relaxed);
+            },
+            &cancel,
+        );
+        assert!(done < items.len());
+
+        // the batch can't take a rotor wired wrong, the machine score
+        // those keys instead of the item being dropped
+        let broken = EnigmaRotorSetting {
+            wiring: EnigmaRotorWiring {
+                forward: "ABC",
+                backward: "ABC",
+            },
+            ..EnigmaRotorSetting::I
+        };
+        let with_broken = [rotors[1], rotors[3], rotors[0], broken];
+        let mut search = EnigmaParallelSearch::new(&with_broken, reflector, 3);
+        search.wires = key.wires.clone();
+        let items = search
+            .work_items_for(&[vec![0, 1, 2, 3]])
+            .into_iter()
+            .take(1)
+            .collect::<Vec<EnigmaWorkItem>>();
+        let found = search.run(
+            &cipher_text,
+            &items,
+            &bigram,
+            &mut |_| {},
+            &AtomicBool::new(false),
+        );
+        assert_eq!(found.len(), 3);
+        let mut machine =
+            EnigmaMachine::new_with_key(&with_broken, reflector, &found[0].key).unwrap();
+        assert_eq!(found[0].score, bigram.score(&machine.encode_str(&cipher_text)));
+
+        // an item pointing past the rotors is skipped, not run on a machine
+        let mut far = items[0].clone();
+        far.key.rotor_order = vec![0, 1, 9];
+        let found = search.run(
+            &cipher_text,
+            &[far],
+            &bigram,
+            &mut |_| {},
+            &AtomicBool::new(false),
+        );
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_work_units() {
+        use std::sync::atomic::AtomicBool;
+
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+        let key = EnigmaKey::parse("4,0,2 AAA RZB").unwrap();
+        let mut enigma_machine = EnigmaMachine::new_with_key(&rotors, reflector, &key).unwrap();
+        let cipher_text = enigma_machine.encode_str(&WEATHER_TEXT[..150]);
+
+        let scoring = EnigmaScoring::Bigram(EnigmaLanguage::German);
+        assert_eq!(EnigmaScoring::parse(&scoring.to_string()), Some(scoring));
+        assert_eq!(
+            EnigmaScoring::parse("quadgram english"),
+            Some(EnigmaScoring::Quadgram(EnigmaLanguage::English))
+        );
+        let search = EnigmaParallelSearch::new(&rotors, reflector, 5);
+        let items = search.work_items_for(&[vec![1, 3, 2], vec![4, 0, 2]]);
+        let units = EnigmaWorkUnit::split(&rotors, reflector, &cipher_text, scoring, 5, &items, 3);
+        assert_eq!(units.len(), 3);
+        assert_eq!(units.iter().map(|u| u.items.len()).sum::<usize>(), 52);
+
+        // every file is read back the same, then run as if somewhere else
+        let results = units
+            .iter()
+            .map(|unit| {
+                let unit = EnigmaWorkUnit::load(&unit.save()).unwrap();
+                let result = unit.process(&mut |_| {}, &AtomicBool::new(false));
+                let saved = result.save();
+                assert_eq!(EnigmaWorkResult::load(&saved), Ok(result));
+                EnigmaWorkResult::load(&saved).unwrap()
+            })
+            .collect::<Vec<EnigmaWorkResult>>();
+        assert_eq!(EnigmaWorkResult::missing(&results[1..]), vec![0]);
+        assert_eq!(EnigmaWorkResult::missing(&results), vec![]);
+
+        // a unit sent back twice doesn't count twice
+        let mut twice = results.clone();
+        twice.push(results[2].clone());
+        let merged = EnigmaWorkResult::merge(&twice, 5);
+        assert_eq!(merged, EnigmaWorkResult::merge(&results, 5));
+        assert_eq!(merged[0].key, key);
+
+        // four rotor unit for the naval M4. with Beta at 'A' and the thin
+        // reflector B it is the same machine as three rotors and reflector B,
+        // also after the slow rotor turn over, Beta doesn't move with it
+        let m4_rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::BETA,
+            EnigmaRotorSetting::GAMMA,
+        ];
+        let thin_b = "ENKQAUYWJICOPBLMDXZVFTHRGS";
+        let m4_key = EnigmaKey::parse("1,0,2,3 AAAA RQVA").unwrap();
+        let mut m4_machine = EnigmaMachine::new_with_key(&m4_rotors, thin_b, &m4_key).unwrap();
+        let m3_key = EnigmaKey::parse("1,0,2 AAA RQV").unwrap();
+        let mut m3_machine = EnigmaMachine::new_with_key(&m4_rotors, reflector, &m3_key).unwrap();
+        let m4_cipher_text = m4_machine.encode_str(&WEATHER_TEXT[..150]);
+        assert_eq!(m4_cipher_text, m3_machine.encode_str(&WEATHER_TEXT[..150]));
+        assert_eq!(m4_machine.get_window(), "LWWA");
+
+        // only the run of start positions with the key in it, for both greek wheels
+        let search = EnigmaParallelSearch::new(&m4_rotors, thin_b, 5);
+        let items = search
+            .work_items_for(&[vec![1, 0, 2, 3], vec![1, 0, 2, 4]])
+            .into_iter()
+            .filter(|item| item.first == ('V' as usize - 'A' as usize) * 26 * 26)
+            .collect::<Vec<EnigmaWorkItem>>();
+        let units = EnigmaWorkUnit::split(
+            &m4_rotors,
+            thin_b,
+            &m4_cipher_text,
+            scoring,
+            5,
+            &items,
+            2,
+        );
+        assert_eq!(units.len(), 2);
+        let results = units
+            .iter()
+            .map(|unit| {
+                let unit = EnigmaWorkUnit::load(&unit.save()).unwrap();
+                assert_eq!(unit.rotors.len(), 5);
+                unit.process(&mut |_| {}, &AtomicBool::new(false))
+            })
+            .collect::<Vec<EnigmaWorkResult>>();
+        assert_eq!(EnigmaWorkResult::merge(&results, 5)[0].key, m4_key);
+
+        // nothing to search still make a unit that read back
+        let empty = EnigmaWorkUnit::split(&rotors, reflector, "", scoring, 5, &items[..1], 1);
+        assert_eq!(EnigmaWorkUnit::load(&empty[0].save()), Ok(empty[0].clone()));
+
+        // item lines a unit can't have, start positions past 26 ^ 3
+        // or too many to add up, ring or window not one letter a rotor
+        let saved = empty[0].save();
+        let no = saved.lines().count() + 1;
+        [
+            "item 17000 1000 0,1,2 AAA AAA",
+            "item 18446744073709551615 1 0,1,2 AAA AAA",
+            "item 0 26 0,1,2 AA AAA",
+            "item 0 26 0,1,2 AAA AAAA",
+        ]
+        .iter()
+        .for_each(|line| {
+            assert_eq!(
+                EnigmaWorkUnit::load(&format!("{}{}\n", saved, line)),
+                Err(EnigmaWorkFileError::InvalidLine(no))
+            );
+        });
+        assert!(EnigmaWorkUnit::load(&format!("{}item 17000 576 0,1,2 AAA AAA\n", saved)).is_ok());
+
+        assert_eq!(
+            EnigmaWorkUnit::load("enigma-work-unit 2\nid 0\n"),
+            Err(EnigmaWorkFileError::InvalidFormat)
+        );
+        assert_eq!(
+            EnigmaWorkResult::load("enigma-work-result 1\nid 0\ncomplete true\n"),
+            Err(EnigmaWorkFileError::MissingField("total".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        let rotors = [
+            EnigmaRotorSetting::I,
+            EnigmaRotorSetting::II,
+            EnigmaRotorSetting::III,
+            EnigmaRotorSetting::IV,
+            EnigmaRotorSetting::V,
+        ];
+        let reflector = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+
+        // random keys, long enough text that every rotor turn over
+        let mut random = EnigmaRandom::new(50);
+        let orders = EnigmaRotorSetting::wheel_orders(rotors.len(), 3);
+        let keys = (0..40)
+            .map(|_| {
+                let wires = random.next_letters(12).into_bytes();
+                EnigmaKey {
+                    rotor_order: orders[random.next_index(orders.len())].clone(),
+                    ring: random.next_letters(3),
+                    window: random.next_letters(3),
+                    // clashing wires are left out by both
+                    wires: wires
+                        .chunks(2)
+                        .filter(|w| w[0] != w[1])
+                        .map(|w| EnigmaPlugBoardWire(w[0] as char, w[1] as char))
+                        .collect(),
+                }
+            })
+            .collect::<Vec<EnigmaKey>>();
+        let text = GERMAN_TEXT.repeat(2);
+
+        let mut batch = EnigmaBatch::new(&rotors, reflector, &keys).unwrap();
+        assert_eq!(batch.len(), keys.len());
+        let decrypts = batch.decrypt_all(&text);
+        for (key, decrypt) in keys.iter().zip(decrypts.iter()) {
+            let mut machine = EnigmaMachine::new_with_key(&rotors, reflector, key).unwrap();
+            assert_eq!(*decrypt, machine.encode_str(&text));
+        }
+
+        let bigram = EnigmaNgramFitness::bigram(EnigmaLanguage::German);
+        let scores = batch.score_all(&text, &bigram);
+        assert_eq!(scores[7], bigram.score(&decrypts[7]));
+
+        batch.set_entry_wheel(EnigmaEntryWheel::QWERTZU);
+        let mut machine = EnigmaMachine::new_with_key(&rotors, reflector, &keys[3]).unwrap();
+        machine.set_entry_wheel(EnigmaEntryWheel::QWERTZU);
+        assert_eq!(batch.decrypt_all(&text)[3], machine.encode_str(&text));
+
+        let four = EnigmaKey::parse("0,1,2,3 AAAA AAAA").unwrap();
+        assert!(matches!(
+            EnigmaBatch::new(&rotors, reflector, &[keys[0].clone(), four]),
+            Err(EnigmaKeyError::RotorCountMismatch)
+        ));
+        // greek wheel of the M4 stay put when the slow rotor turn over
+        let m4_rotors = [rotors[0], rotors[1], rotors[2], EnigmaRotorSetting::BETA];
+        let thin_b = "ENKQAUYWJICOPBLMDXZVFTHRGS";
+        let m4_key = EnigmaKey::parse("1,0,2,3 AAAA RQVC").unwrap();
+        let batch = EnigmaBatch::new(&m4_rotors, thin_b, std::slice::from_ref(&m4_key)).unwrap();
+        let mut machine = EnigmaMachine::new_with_key(&m4_rotors, thin_b, &m4_key).unwrap();
+        assert_eq!(batch.decrypt_all(&text)[0], machine.encode_str(&text));
+        assert!(machine.get_window().ends_with('C'));
+
+        let past = EnigmaKey::parse("0,1,7 AAA AAA").unwrap();
+        assert!(matches!(
+            EnigmaBatch::new(&rotors, reflector, &[past]),
+            Err(EnigmaKeyError::InvalidRotor(7))
+        ));
+    }
 }